@@ -0,0 +1,416 @@
+// Per-column state derived from the schema once, at engine construction.
+// Keeps the hot path (`validate_field`) free of parsing/compilation work.
+
+use crate::schema::{ColumnSpec, ColumnType, Condition, DateFormat, Schema};
+use crate::temporal::{self, DateBound};
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::rc::Rc;
+use time::format_description::OwnedFormatItem;
+use time::{Date, Month, OffsetDateTime, UtcOffset};
+
+#[derive(Debug, Clone)]
+pub struct CompiledColumn {
+    pub pattern: Option<Regex>,
+    pub allowed: Option<AllowedValues>,
+    pub bools: Option<BoolTokens>,
+    // Offset for naive datetime/time values
+    pub timezone: UtcOffset,
+    // Accepted layouts of a date column, tried in order
+    pub date_formats: Vec<DateLayout>,
+    // Resolved minDate/maxDate
+    pub min_date: Option<DateBound>,
+    pub max_date: Option<DateBound>,
+    // requiredIf / requiredUnless / forbiddenIf
+    pub required_if: Option<RowCondition>,
+    pub required_unless: Option<RowCondition>,
+    pub forbidden_if: Option<RowCondition>,
+}
+
+/// A `Condition` with the column resolved to its schema index.
+#[derive(Debug, Clone)]
+pub struct RowCondition {
+    pub column: usize,
+    // None: the column just has to be non-empty
+    pub one_of: Option<Vec<String>>,
+}
+
+impl RowCondition {
+    /// `value` is the other column's trimmed text (empty if missing).
+    pub fn holds(&self, value: &[u8]) -> bool {
+        match &self.one_of {
+            Some(values) => values.iter().any(|v| v.as_bytes() == value),
+            None => !value.is_empty(),
+        }
+    }
+}
+
+/// `allowed` / `allowedMap` as one hash lookup from accepted input to the
+/// canonical value.
+#[derive(Debug, Clone)]
+pub struct AllowedValues {
+    // Keys are lowercased when case-insensitive
+    values: HashMap<Box<str>, Rc<str>>,
+    case_insensitive: bool,
+}
+
+impl AllowedValues {
+    pub fn canonical(&self, s: &str) -> Option<&Rc<str>> {
+        if self.case_insensitive {
+            self.values.get(s.to_lowercase().as_str())
+        } else {
+            self.values.get(s)
+        }
+    }
+}
+
+impl CompiledColumn {
+    pub fn parse_date(&self, s: &str) -> Option<Date> {
+        self.date_formats.iter().find_map(|f| f.parse(s))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DateLayout {
+    Builtin(DateFormat),
+    Custom(OwnedFormatItem),
+}
+
+impl DateLayout {
+    pub fn parse(&self, s: &str) -> Option<Date> {
+        match self {
+            DateLayout::Builtin(fmt) => crate::parse_date(s, *fmt),
+            DateLayout::Custom(item) => Date::parse(s, item).ok(),
+        }
+    }
+
+    pub fn format(&self, date: Date) -> Option<String> {
+        match self {
+            DateLayout::Builtin(fmt) => Some(crate::format_date(date, *fmt)),
+            DateLayout::Custom(item) => date.format(item).ok(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BoolTokens {
+    truthy: Vec<String>,
+    falsy: Vec<String>,
+    case_sensitive: bool,
+}
+
+impl BoolTokens {
+    pub fn parse(&self, s: &str) -> Option<bool> {
+        let matches = |t: &String| {
+            if self.case_sensitive {
+                t == s
+            } else {
+                // tokens are stored lowercased
+                s.chars().flat_map(char::to_lowercase).eq(t.chars())
+            }
+        };
+        if self.truthy.iter().any(matches) {
+            Some(true)
+        } else if self.falsy.iter().any(matches) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// `now` resolves relative date bounds; without it they are an error.
+pub fn compile_columns(
+    schema: &Schema,
+    now: Option<OffsetDateTime>,
+) -> Result<Vec<CompiledColumn>, String> {
+    schema
+        .columns
+        .iter()
+        .map(|col| {
+            if col.min.is_some() || col.max.is_some() {
+                if !matches!(col.col_type, ColumnType::Int | ColumnType::Decimal) {
+                    return Err(format!(
+                        "column \"{}\": min/max only apply to int and decimal columns",
+                        col.name
+                    ));
+                }
+                if let (Some(lo), Some(hi)) = (col.min, col.max) {
+                    if lo > hi {
+                        return Err(format!("column \"{}\": min is greater than max", col.name));
+                    }
+                }
+            }
+
+            if col.exact_len.is_some() && (col.min_len.is_some() || col.max_len.is_some()) {
+                return Err(format!(
+                    "column \"{}\": exactLen cannot be combined with minLen/maxLen",
+                    col.name
+                ));
+            }
+            if let (Some(lo), Some(hi)) = (col.min_len, col.max_len) {
+                if lo > hi {
+                    return Err(format!(
+                        "column \"{}\": minLen is greater than maxLen",
+                        col.name
+                    ));
+                }
+            }
+
+            let scale = col.decimal_scale();
+            if scale > 28 {
+                return Err(format!("column \"{}\": scale must be at most 28", col.name));
+            }
+            if let Some(digits) = col.total_digits {
+                if digits == 0 || digits < scale {
+                    return Err(format!(
+                        "column \"{}\": totalDigits must be at least 1 and not less than scale",
+                        col.name
+                    ));
+                }
+            }
+
+            let bools = if matches!(col.col_type, ColumnType::Bool) {
+                Some(compile_bools(col)?)
+            } else if col.true_values.is_some() || col.false_values.is_some() {
+                return Err(format!(
+                    "column \"{}\": trueValues/falseValues only apply to bool columns",
+                    col.name
+                ));
+            } else {
+                None
+            };
+
+            let timezone = match &col.timezone {
+                Some(_) if !matches!(col.col_type, ColumnType::Datetime | ColumnType::Time) => {
+                    return Err(format!(
+                        "column \"{}\": timezone only applies to datetime and time columns",
+                        col.name
+                    ));
+                }
+                Some(tz) => temporal::parse_timezone(tz).ok_or_else(|| {
+                    format!(
+                        "column \"{}\": timezone must be \"UTC\" or an offset like \"+02:00\"",
+                        col.name
+                    )
+                })?,
+                None => UtcOffset::UTC,
+            };
+
+            let date_formats = if matches!(col.col_type, ColumnType::Date) {
+                compile_date_formats(col)?
+            } else {
+                Vec::new()
+            };
+
+            let (min_date, max_date) = compile_date_bounds(col, now)?;
+
+            let condition = |c: &Option<Condition>, what: &str| match c {
+                Some(c) => compile_condition(schema, col, c, what).map(Some),
+                None => Ok(None),
+            };
+            let required_if = condition(&col.required_if, "requiredIf")?;
+            let required_unless = condition(&col.required_unless, "requiredUnless")?;
+            let forbidden_if = condition(&col.forbidden_if, "forbiddenIf")?;
+
+            let allowed = compile_allowed(col)?;
+
+            let pattern = match &col.pattern {
+                // Anchored: the pattern describes the whole value, not a substring
+                Some(p) => Some(
+                    Regex::new(&format!("^(?:{p})$"))
+                        .map_err(|e| format!("column \"{}\": invalid pattern: {e}", col.name))?,
+                ),
+                None => None,
+            };
+
+            Ok(CompiledColumn {
+                pattern,
+                allowed,
+                bools,
+                timezone,
+                date_formats,
+                min_date,
+                max_date,
+                required_if,
+                required_unless,
+                forbidden_if,
+            })
+        })
+        .collect()
+}
+
+fn compile_allowed(col: &ColumnSpec) -> Result<Option<AllowedValues>, String> {
+    if col.allowed.is_empty() && col.allowed_map.is_empty() {
+        if col.allowed_case_insensitive {
+            return Err(format!(
+                "column \"{}\": allowedCaseInsensitive needs allowed or allowedMap",
+                col.name
+            ));
+        }
+        return Ok(None);
+    }
+
+    let key = |s: &str| -> Box<str> {
+        if col.allowed_case_insensitive {
+            s.to_lowercase().into()
+        } else {
+            s.into()
+        }
+    };
+
+    let mut values: HashMap<Box<str>, Rc<str>> = HashMap::new();
+    let canonical = col
+        .allowed
+        .iter()
+        .map(|v| (v, v))
+        .chain(col.allowed_map.keys().map(|v| (v, v)));
+    let synonyms = col
+        .allowed_map
+        .iter()
+        .flat_map(|(v, inputs)| inputs.iter().map(move |input| (input, v)));
+
+    for (input, value) in canonical.chain(synonyms) {
+        let value: Rc<str> = value.as_str().into();
+        if let Some(prev) = values.insert(key(input), value.clone()) {
+            if prev != value {
+                return Err(format!(
+                    "column \"{}\": \"{input}\" maps to both \"{prev}\" and \"{value}\"",
+                    col.name
+                ));
+            }
+        }
+    }
+
+    Ok(Some(AllowedValues {
+        values,
+        case_insensitive: col.allowed_case_insensitive,
+    }))
+}
+
+fn compile_condition(
+    schema: &Schema,
+    col: &ColumnSpec,
+    c: &Condition,
+    what: &str,
+) -> Result<RowCondition, String> {
+    let column = schema
+        .columns
+        .iter()
+        .position(|other| other.name == c.column)
+        .filter(|_| c.column != col.name)
+        .ok_or_else(|| {
+            format!(
+                "column \"{}\": {what} must name another column, not \"{}\"",
+                col.name, c.column
+            )
+        })?;
+
+    let one_of = match (&c.equals, &c.one_of) {
+        (Some(_), Some(_)) => {
+            return Err(format!(
+                "column \"{}\": {what} takes either equals or in, not both",
+                col.name
+            ))
+        }
+        (Some(v), None) => Some(vec![v.trim().to_string()]),
+        (None, Some(vs)) => Some(vs.iter().map(|v| v.trim().to_string()).collect()),
+        (None, None) => None,
+    };
+
+    Ok(RowCondition { column, one_of })
+}
+
+fn compile_date_bounds(
+    col: &ColumnSpec,
+    now: Option<OffsetDateTime>,
+) -> Result<(Option<DateBound>, Option<DateBound>), String> {
+    if col.min_date.is_none() && col.max_date.is_none() {
+        return Ok((None, None));
+    }
+    if !matches!(col.col_type, ColumnType::Date | ColumnType::Datetime) {
+        return Err(format!(
+            "column \"{}\": minDate/maxDate only apply to date and datetime columns",
+            col.name
+        ));
+    }
+
+    let resolve = |bound: &Option<String>| match bound {
+        Some(b) => temporal::parse_date_bound(b, now)
+            .map(Some)
+            .map_err(|e| format!("column \"{}\": {e}", col.name)),
+        None => Ok(None),
+    };
+    let (lo, hi) = (resolve(&col.min_date)?, resolve(&col.max_date)?);
+
+    if let (Some(DateBound::Day(lo)), Some(DateBound::Day(hi))) = (lo, hi) {
+        if lo > hi {
+            return Err(format!("column \"{}\": minDate is after maxDate", col.name));
+        }
+    }
+    Ok((lo, hi))
+}
+
+fn compile_date_formats(col: &ColumnSpec) -> Result<Vec<DateLayout>, String> {
+    if col.date_format.is_empty() {
+        return Ok(vec![DateLayout::Builtin(DateFormat::YmdDash)]);
+    }
+
+    col.date_format
+        .iter()
+        .map(|f| {
+            if let Some(builtin) = DateFormat::from_name(f) {
+                return Ok(DateLayout::Builtin(builtin));
+            }
+            let item = time::format_description::parse_owned::<2>(f).map_err(|e| {
+                format!("column \"{}\": invalid dateFormat \"{f}\": {e}", col.name)
+            })?;
+            let layout = DateLayout::Custom(item);
+
+            // Must describe a whole calendar date and nothing else: a sample
+            // date has to survive formatting and parsing back
+            let sample = Date::from_calendar_date(2024, Month::January, 31).unwrap();
+            match layout.format(sample).and_then(|text| layout.parse(&text)) {
+                Some(d) if d == sample => Ok(layout),
+                _ => Err(format!(
+                    "column \"{}\": dateFormat \"{f}\" must describe a full date (year, month, day)",
+                    col.name
+                )),
+            }
+        })
+        .collect()
+}
+
+fn compile_bools(col: &ColumnSpec) -> Result<BoolTokens, String> {
+    let (truthy, falsy) = col.bool_values();
+    if truthy.is_empty() || falsy.is_empty() {
+        return Err(format!(
+            "column \"{}\": trueValues and falseValues must not be empty",
+            col.name
+        ));
+    }
+
+    let fold = |t: &str| {
+        if col.bool_case_sensitive {
+            t.to_string()
+        } else {
+            t.to_lowercase()
+        }
+    };
+    let truthy: Vec<String> = truthy.into_iter().map(fold).collect();
+    let falsy: Vec<String> = falsy.into_iter().map(fold).collect();
+
+    if let Some(t) = truthy.iter().find(|t| falsy.contains(t)) {
+        return Err(format!(
+            "column \"{}\": \"{t}\" is both a true and a false value",
+            col.name
+        ));
+    }
+
+    Ok(BoolTokens {
+        truthy,
+        falsy,
+        case_sensitive: col.bool_case_sensitive,
+    })
+}
@@ -0,0 +1,248 @@
+// Optional rich error stream: offending value, position in the input and
+// an "expected" hint, kept alongside the packed errors (which stay the fast
+// path and are unaffected).
+
+use crate::column::CompiledColumn;
+use crate::errors::{ColKind, ErrorCode, PackedError};
+use crate::schema::{ColumnSpec, ColumnType, DateFormat, RecordTerminator, Schema};
+use crate::structure::LineBreaks;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorDetail {
+    pub row: u32,
+    pub col: u32,
+    pub kind: &'static str, // "schema" | "input"
+    pub code: &'static str,
+    // Raw field text (lossy UTF-8), cut to the configured limit
+    pub value: Option<String>,
+    pub value_truncated: bool,
+    // Physical line (1-based) and byte offset where the record starts;
+    // None for XLSX input
+    pub line: Option<u64>,
+    pub byte_offset: Option<u64>,
+    pub expected: Option<String>,
+}
+
+impl ErrorDetail {
+    pub fn new(e: &PackedError, at: Option<(u64, u64)>, expected: Option<String>) -> Self {
+        ErrorDetail {
+            row: e.row,
+            col: e.col,
+            kind: match e.kind {
+                ColKind::Schema => "schema",
+                ColKind::Input => "input",
+            },
+            code: e.code.as_str(),
+            value: None,
+            value_truncated: false,
+            line: at.map(|(line, _)| line),
+            byte_offset: at.map(|(_, offset)| offset),
+            expected,
+        }
+    }
+
+    pub fn set_value(&mut self, raw: &[u8], limit: usize) {
+        let text = String::from_utf8_lossy(raw);
+        match text.char_indices().nth(limit) {
+            Some((cut, _)) => {
+                self.value = Some(text[..cut].to_string());
+                self.value_truncated = true;
+            }
+            None => self.value = Some(text.into_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gap {
+    Between, // terminators / blank lines before the next record
+    Comment, // inside a skipped comment line
+    Record,  // inside a record
+}
+
+/// Tracks where records start in the parsed byte stream. csv-core skips
+/// blank and comment lines silently, so those are stepped over here too.
+#[derive(Debug, Clone)]
+pub struct Locator {
+    consumed: u64,
+    line: u64,
+    breaks: LineBreaks,
+    state: Gap,
+    record: (u64, u64), // (line, offset) of the current record
+    // No byte positions (XLSX input)
+    detached: bool,
+}
+
+impl Default for Locator {
+    fn default() -> Self {
+        Locator {
+            consumed: 0,
+            line: 1,
+            breaks: LineBreaks::default(),
+            state: Gap::Between,
+            record: (1, 0),
+            detached: false,
+        }
+    }
+}
+
+impl Locator {
+    /// Called with the input about to be handed to csv-core; records the
+    /// start of the next record if it begins in `input`.
+    pub fn scan(&mut self, input: &[u8], schema: &Schema) {
+        if self.state == Gap::Record {
+            return;
+        }
+        let is_term = |b: u8| match schema.terminator {
+            RecordTerminator::Crlf => b == b'\n' || b == b'\r',
+            RecordTerminator::Byte(t) => b == t,
+        };
+
+        let (mut line, mut breaks) = (self.line, self.breaks);
+        for (i, &b) in input.iter().enumerate() {
+            match self.state {
+                Gap::Comment if is_term(b) => self.state = Gap::Between,
+                Gap::Comment => {}
+                _ if is_term(b) => {}
+                _ if Some(b) == schema.comment => self.state = Gap::Comment,
+                _ => {
+                    self.state = Gap::Record;
+                    self.record = (line, self.consumed + i as u64);
+                    return;
+                }
+            }
+            if breaks.ends_line(b, schema.terminator) {
+                line += 1;
+            }
+        }
+    }
+
+    /// Account for `consumed` bytes taken by csv-core.
+    pub fn advance(&mut self, consumed: &[u8], schema: &Schema) {
+        self.consumed += consumed.len() as u64;
+        for &b in consumed {
+            if self.breaks.ends_line(b, schema.terminator) {
+                self.line += 1;
+            }
+        }
+    }
+
+    pub fn end_record(&mut self) {
+        self.state = Gap::Between;
+    }
+
+    pub fn detach(&mut self) {
+        self.detached = true;
+    }
+
+    /// (line, byte offset) of the current record.
+    pub fn record(&self) -> Option<(u64, u64)> {
+        (!self.detached).then_some(self.record)
+    }
+}
+
+/// What the column wanted, phrased for a person reading the error.
+pub fn expected_hint(
+    schema: &Schema,
+    compiled: &[CompiledColumn],
+    code: ErrorCode,
+    col: u32,
+    detail: u32,
+) -> Option<String> {
+    // Schema-kind errors only: `col` is a schema column index
+    let c = schema.columns.get(col as usize)?;
+
+    Some(match code {
+        ErrorCode::MissingRequiredColumn => format!("column \"{}\"", c.name),
+        ErrorCode::MissingRequired => "a value".to_string(),
+        ErrorCode::InvalidType => type_hint(c),
+        ErrorCode::MaxLengthExceeded => format!("at most {} {}", c.max_len?, c.length_unit.name()),
+        ErrorCode::MinLengthNotMet => format!("at least {} {}", c.min_len?, c.length_unit.name()),
+        ErrorCode::ExactLengthMismatch => {
+            format!("exactly {} {}", c.exact_len?, c.length_unit.name())
+        }
+        ErrorCode::DisallowedCharacter => {
+            let classes: Vec<&str> = c.char_class.iter().map(|cc| cc.describe()).collect();
+            format!("only {} characters", classes.join(", "))
+        }
+        ErrorCode::NotAllowed => format!("one of: {}", c.allowed_values().join(", ")),
+        ErrorCode::InvalidUtf8 => "valid UTF-8 text".to_string(),
+        ErrorCode::PatternMismatch => format!("match /{}/", c.pattern.as_deref()?),
+        ErrorCode::OutOfRange => match c.col_type {
+            ColumnType::Date | ColumnType::Datetime => date_range_hint(&compiled[col as usize])?,
+            _ => range_hint(c)?,
+        },
+        ErrorCode::DuplicateValue => format!("a unique value (first seen in row {detail})"),
+        ErrorCode::ScaleExceeded => format!("at most {} decimal places", c.decimal_scale()),
+        ErrorCode::TooManyDigits => format!("at most {} digits", c.total_digits?),
+        ErrorCode::RuleViolation => schema.rules.get(detail as usize)?.message().to_string(),
+        ErrorCode::Forbidden => "no value".to_string(),
+        ErrorCode::ColumnOutOfOrder => format!("column \"{}\" at position {}", c.name, col + 1),
+        ErrorCode::FormulaInjection => "text not starting with =, +, -, @, tab or CR".to_string(),
+        ErrorCode::ControlCharacter => "no control characters".to_string(),
+        ErrorCode::NotInLookup => format!("a value from lookup \"{}\"", c.lookup.as_deref()?),
+        // Input-kind only
+        ErrorCode::ExtraColumn
+        | ErrorCode::RowTooShort
+        | ErrorCode::RowTooLong
+        | ErrorCode::UnexpectedQuote
+        | ErrorCode::UnterminatedQuote
+        | ErrorCode::FieldTooLarge
+        | ErrorCode::RecordTooLarge
+        | ErrorCode::DuplicateHeader
+        | ErrorCode::EmptyHeader => return None,
+    })
+}
+
+fn type_hint(c: &ColumnSpec) -> String {
+    match c.col_type {
+        ColumnType::String => "text".to_string(),
+        ColumnType::Int => "an integer".to_string(),
+        ColumnType::Decimal => "a decimal number".to_string(),
+        ColumnType::Bool => {
+            let (truthy, falsy) = c.bool_values();
+            format!("true ({}) or false ({})", truthy.join("/"), falsy.join("/"))
+        }
+        ColumnType::Datetime => "an ISO 8601 date-time (YYYY-MM-DDTHH:MM:SS[+HH:MM])".to_string(),
+        ColumnType::Time => "a time of day (HH:MM[:SS][+HH:MM])".to_string(),
+        ColumnType::Date => {
+            let layouts: Vec<&str> = if c.date_format.is_empty() {
+                vec![DateFormat::YmdDash.layout()]
+            } else {
+                c.date_format
+                    .iter()
+                    .map(|f| match DateFormat::from_name(f) {
+                        Some(builtin) => builtin.layout(),
+                        None => f.as_str(),
+                    })
+                    .collect()
+            };
+            format!("a date ({})", layouts.join(" or "))
+        }
+    }
+}
+
+fn range_hint(c: &ColumnSpec) -> Option<String> {
+    let lo = c
+        .min
+        .map(|v| format!("{} {v}", if c.exclusive_min { ">" } else { ">=" }));
+    let hi = c
+        .max
+        .map(|v| format!("{} {v}", if c.exclusive_max { "<" } else { "<=" }));
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => Some(format!("{lo} and {hi}")),
+        (lo, hi) => lo.or(hi),
+    }
+}
+
+fn date_range_hint(c: &CompiledColumn) -> Option<String> {
+    let lo = c.min_date.map(|b| format!("on or after {b}"));
+    let hi = c.max_date.map(|b| format!("on or before {b}"));
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => Some(format!("{lo} and {hi}")),
+        (lo, hi) => lo.or(hi),
+    }
+}
@@ -11,9 +11,66 @@ pub enum ErrorCode {
     InvalidUtf8 = 5,
     MissingRequiredColumn = 6,
     ExtraColumn = 7,
+    PatternMismatch = 8,
+    OutOfRange = 9,
+    DuplicateValue = 10,
+    ScaleExceeded = 11,
+    TooManyDigits = 12,
+    RuleViolation = 13,
+    Forbidden = 14,
+    NotInLookup = 15,
+    MinLengthNotMet = 16,
+    ExactLengthMismatch = 17,
+    DisallowedCharacter = 18,
+    FormulaInjection = 19,
+    ControlCharacter = 20,
+    RowTooShort = 21,
+    RowTooLong = 22,
+    UnexpectedQuote = 23,
+    UnterminatedQuote = 24,
+    FieldTooLarge = 25,
+    RecordTooLarge = 26,
+    DuplicateHeader = 27,
+    EmptyHeader = 28,
+    ColumnOutOfOrder = 29,
 }
 
 impl ErrorCode {
+    pub fn from_u8(code: u8) -> Option<ErrorCode> {
+        Some(match code {
+            1 => ErrorCode::MissingRequired,
+            2 => ErrorCode::InvalidType,
+            3 => ErrorCode::MaxLengthExceeded,
+            4 => ErrorCode::NotAllowed,
+            5 => ErrorCode::InvalidUtf8,
+            6 => ErrorCode::MissingRequiredColumn,
+            7 => ErrorCode::ExtraColumn,
+            8 => ErrorCode::PatternMismatch,
+            9 => ErrorCode::OutOfRange,
+            10 => ErrorCode::DuplicateValue,
+            11 => ErrorCode::ScaleExceeded,
+            12 => ErrorCode::TooManyDigits,
+            13 => ErrorCode::RuleViolation,
+            14 => ErrorCode::Forbidden,
+            15 => ErrorCode::NotInLookup,
+            16 => ErrorCode::MinLengthNotMet,
+            17 => ErrorCode::ExactLengthMismatch,
+            18 => ErrorCode::DisallowedCharacter,
+            19 => ErrorCode::FormulaInjection,
+            20 => ErrorCode::ControlCharacter,
+            21 => ErrorCode::RowTooShort,
+            22 => ErrorCode::RowTooLong,
+            23 => ErrorCode::UnexpectedQuote,
+            24 => ErrorCode::UnterminatedQuote,
+            25 => ErrorCode::FieldTooLarge,
+            26 => ErrorCode::RecordTooLarge,
+            27 => ErrorCode::DuplicateHeader,
+            28 => ErrorCode::EmptyHeader,
+            29 => ErrorCode::ColumnOutOfOrder,
+            _ => return None,
+        })
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             ErrorCode::MissingRequired => "MissingRequired",
@@ -23,6 +80,28 @@ impl ErrorCode {
             ErrorCode::InvalidUtf8 => "InvalidUtf8",
             ErrorCode::MissingRequiredColumn => "MissingRequiredColumn",
             ErrorCode::ExtraColumn => "ExtraColumn",
+            ErrorCode::PatternMismatch => "PatternMismatch",
+            ErrorCode::OutOfRange => "OutOfRange",
+            ErrorCode::DuplicateValue => "DuplicateValue",
+            ErrorCode::ScaleExceeded => "ScaleExceeded",
+            ErrorCode::TooManyDigits => "TooManyDigits",
+            ErrorCode::RuleViolation => "RuleViolation",
+            ErrorCode::Forbidden => "Forbidden",
+            ErrorCode::NotInLookup => "NotInLookup",
+            ErrorCode::MinLengthNotMet => "MinLengthNotMet",
+            ErrorCode::ExactLengthMismatch => "ExactLengthMismatch",
+            ErrorCode::DisallowedCharacter => "DisallowedCharacter",
+            ErrorCode::FormulaInjection => "FormulaInjection",
+            ErrorCode::ControlCharacter => "ControlCharacter",
+            ErrorCode::RowTooShort => "RowTooShort",
+            ErrorCode::RowTooLong => "RowTooLong",
+            ErrorCode::UnexpectedQuote => "UnexpectedQuote",
+            ErrorCode::UnterminatedQuote => "UnterminatedQuote",
+            ErrorCode::FieldTooLarge => "FieldTooLarge",
+            ErrorCode::RecordTooLarge => "RecordTooLarge",
+            ErrorCode::DuplicateHeader => "DuplicateHeader",
+            ErrorCode::EmptyHeader => "EmptyHeader",
+            ErrorCode::ColumnOutOfOrder => "ColumnOutOfOrder",
         }
     }
 }
@@ -36,17 +115,28 @@ pub enum ColKind {
 
 #[derive(Clone, Copy, Debug)]
 pub struct PackedError {
+    // Physical line where the record starts (CSV) or sheet row number
+    // (XLSX); 0 for header-level errors
     pub row: u32,
     pub col: u32,
     pub code: ErrorCode,
     pub kind: ColKind,
+    // Code-specific extra value, 0 when unused
+    // (DuplicateValue: row where the value first appeared,
+    // RuleViolation: index into schema.rules)
+    pub detail: u32,
 }
 
 impl PackedError {
     /// word0 = row
     /// word1 = (kind<<31) | (col<<8) | code
-    pub fn to_words(self) -> [u32; 2] {
+    /// word2 = detail
+    pub fn to_words(self) -> [u32; 3] {
         let kind_bit = (self.kind as u32) << 31;
-        [self.row, kind_bit | (self.col << 8) | (self.code as u32)]
+        [
+            self.row,
+            kind_bit | (self.col << 8) | (self.code as u32),
+            self.detail,
+        ]
     }
 }
@@ -0,0 +1,124 @@
+// Header-to-schema column matching (names and aliases, per `headerMatching`).
+
+use crate::schema::{DuplicateHeaders, HeaderMatching, Schema};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchedBy {
+    Name,
+    Alias,
+}
+
+/// Comparison key for a header or column name under `mode`.
+pub fn match_key(name: &str, mode: HeaderMatching) -> String {
+    match mode {
+        HeaderMatching::Exact => name.to_string(),
+        HeaderMatching::CaseInsensitive => name.to_lowercase(),
+        // "Unit Price", "unit_price" and "UNIT-PRICE." all become "unitprice"
+        HeaderMatching::Normalized => name
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect(),
+    }
+}
+
+/// Every column name and alias must pick out different headers under
+/// `headerMatching`, or which column a header lands in would depend on
+/// schema order.
+pub fn check_names(schema: &Schema) -> Result<(), String> {
+    let mode = schema.header_matching;
+    // (key, spelling, column index)
+    let mut seen: Vec<(String, &str, usize)> = Vec::new();
+    for (idx, col) in schema.columns.iter().enumerate() {
+        for name in std::iter::once(&col.name).chain(&col.aliases) {
+            let key = match_key(name, mode);
+            match seen.iter().find(|(k, _, _)| *k == key) {
+                Some((_, _, other)) if *other == idx => {}
+                Some((_, spelling, other)) => {
+                    return Err(format!(
+                        "\"{name}\" (column \"{}\") matches the same headers as \"{spelling}\" (column \"{}\")",
+                        col.name, schema.columns[*other].name
+                    ))
+                }
+                None => seen.push((key, name, idx)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// For each input header, the index of an earlier header with the same
+/// comparison key (empty headers are never duplicates).
+pub fn duplicates(input_names: &[String], mode: HeaderMatching) -> Vec<Option<usize>> {
+    let keys: Vec<String> = input_names.iter().map(|n| match_key(n, mode)).collect();
+    keys.iter()
+        .enumerate()
+        .map(|(i, k)| {
+            if input_names[i].is_empty() {
+                return None;
+            }
+            keys[..i].iter().position(|other| other == k)
+        })
+        .collect()
+}
+
+/// Resolve each input header to a schema column. Each column is claimed at
+/// most once: name matches first, then aliases; among same-named inputs the
+/// first or last wins per `duplicateHeaders` ("all" maps every copy).
+/// Headers left over are unmatched (extra columns).
+pub fn resolve(input_names: &[String], schema: &Schema) -> Vec<Option<(usize, MatchedBy)>> {
+    let mode = schema.header_matching;
+    let keys: Vec<String> = input_names.iter().map(|n| match_key(n, mode)).collect();
+    let pick = |matches: &dyn Fn(usize) -> bool| match schema.duplicate_headers {
+        DuplicateHeaders::Last => (0..keys.len()).rev().find(|&i| matches(i)),
+        _ => (0..keys.len()).find(|&i| matches(i)),
+    };
+
+    let mut resolved = vec![None; input_names.len()];
+    let mut claimed = vec![false; schema.columns.len()];
+
+    for (schema_idx, col) in schema.columns.iter().enumerate() {
+        let name = match_key(&col.name, mode);
+        if let Some(input_i) = pick(&|i| keys[i] == name) {
+            if resolved[input_i].is_none() {
+                resolved[input_i] = Some((schema_idx, MatchedBy::Name));
+                claimed[schema_idx] = true;
+            }
+        }
+    }
+
+    for (schema_idx, col) in schema.columns.iter().enumerate() {
+        if claimed[schema_idx] {
+            continue;
+        }
+        let aliases: Vec<String> = col.aliases.iter().map(|a| match_key(a, mode)).collect();
+        if let Some(input_i) = pick(&|i| resolved[i].is_none() && aliases.contains(&keys[i])) {
+            resolved[input_i] = Some((schema_idx, MatchedBy::Alias));
+        }
+    }
+
+    if schema.duplicate_headers == DuplicateHeaders::All {
+        for (input_i, first) in duplicates(input_names, mode).into_iter().enumerate() {
+            if let Some(first) = first {
+                if resolved[input_i].is_none() {
+                    resolved[input_i] = resolved[first];
+                }
+            }
+        }
+    }
+
+    resolved
+}
+
+/// Lenient check used when guessing header presence: does `field` name any
+/// schema column (by name or alias, normalized)?
+pub fn names_any_column(field: &str, schema: &Schema) -> bool {
+    let key = match_key(field, HeaderMatching::Normalized);
+    !key.is_empty()
+        && schema.columns.iter().any(|c| {
+            match_key(&c.name, HeaderMatching::Normalized) == key
+                || c.aliases
+                    .iter()
+                    .any(|a| match_key(a, HeaderMatching::Normalized) == key)
+        })
+}
@@ -0,0 +1,44 @@
+// CSV/formula injection and control characters in string cells.
+//
+// Spreadsheet apps run a cell as a formula when it starts with = + - @
+// (and tab / CR are stripped ahead of that check). Both the raw cell and
+// the trimmed value we emit are looked at.
+
+/// First character that makes the cell a formula trigger, if any.
+pub fn formula_trigger(raw: &[u8], trimmed: &str) -> Option<char> {
+    match raw.first() {
+        Some(&b) if b == b'\t' || b == b'\r' => Some(b as char),
+        _ => trimmed.chars().next().filter(|c| is_trigger(*c)),
+    }
+}
+
+/// First control character other than tab, CR and LF (which quoted CSV
+/// fields may legitimately contain).
+pub fn control_char(s: &str) -> Option<char> {
+    s.chars().find(|&c| is_disallowed_control(c))
+}
+
+/// Output form of a trimmed string value: control characters dropped and a
+/// leading `'` added when the cell would start like a formula.
+pub fn neutralize(s: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.len() + 1);
+    let mut cleaned = s.chars().filter(|&c| !is_disallowed_control(c));
+    if let Some(first) = cleaned.clone().next() {
+        if is_trigger(first) {
+            out.push(b'\'');
+        }
+    }
+    let mut buf = [0u8; 4];
+    for c in &mut cleaned {
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    out
+}
+
+fn is_trigger(c: char) -> bool {
+    matches!(c, '=' | '+' | '-' | '@')
+}
+
+fn is_disallowed_control(c: char) -> bool {
+    c.is_control() && !matches!(c, '\t' | '\n' | '\r')
+}
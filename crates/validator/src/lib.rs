@@ -1,14 +1,37 @@
 // crates/validator/src/lib.rs
-mod schema;
+mod column;
+mod details;
 mod errors;
-
+mod header;
+mod injection;
+mod lookup;
+mod rules;
+mod schema;
+mod sniff;
+mod structure;
+mod temporal;
+mod transcode;
+mod unique;
+mod xlsx;
+
+use column::CompiledColumn;
+use details::{ErrorDetail, Locator};
 use errors::{ColKind, ErrorCode, PackedError};
-use schema::{ColumnType, DateFormat, Progress, Schema};
+use header::MatchedBy;
+use lookup::LookupSets;
+use schema::{
+    ColumnOrder, ColumnType, DateFormat, Delimiter, DialectInfo, HeaderMatch, InjectionPolicy,
+    Progress, RaggedRows, RecordTerminator, ScaleMode, Schema,
+};
 
 use csv_core::{ReadRecordResult, Reader, ReaderBuilder, Terminator};
 use rust_decimal::Decimal;
 use rust_decimal::RoundingStrategy;
+use std::cmp::Ordering;
 use std::str;
+use structure::{Problem, ProblemKind, Scanner};
+use transcode::Transcoder;
+use unique::KeySet;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -16,9 +39,18 @@ pub struct ValidatorEngine {
     schema: Schema,
     rdr: Reader,
 
+    // Per-column compiled constraints, indexed like schema.columns
+    compiled: Vec<CompiledColumn>,
+
     // Reused output buffers for csv-core
     out: Vec<u8>,
     ends: Vec<usize>,
+    // How much of out/ends the current (partial) record already fills;
+    // csv-core resumes a record across calls, so we must not overwrite it
+    out_len: usize,
+    ends_len: usize,
+    // Input copy handed to csv-core when comment lines need rewriting
+    fed: Vec<u8>,
 
     // Header state
     header_parsed: bool,
@@ -35,13 +67,32 @@ pub struct ValidatorEngine {
     // For JS: input (CSV) header names in input order (only when has_headers=true)
     input_header_names: Vec<String>,
 
+    // For JS: how each input header was matched to the schema
+    header_matches: Vec<HeaderMatch>,
+
     // Row counter (data rows only, 1-based)
     data_row: u32,
+    // Row errors on the current record report: the physical line where it
+    // starts for CSV, the sheet row number (`sheet_row`) for XLSX
+    row: u32,
+    sheet_row: Option<u32>,
 
     // Errors collected (drained to JS)
     errors: Vec<PackedError>,
     max_errors: u32,
 
+    // Row-level rules, the typed row values they read (by schema column),
+    // and which fields of the current row failed validation
+    rules: Vec<rules::Rule>,
+    rule_values: Vec<rules::Value>,
+    row_invalid: Vec<bool>,
+
+    // Rich error details (opt-in): value length limit, queue, and where
+    // records start in the parsed stream
+    detail_value_limit: Option<usize>,
+    error_details: Vec<ErrorDetail>,
+    locator: Locator,
+
     // Normalized output (optional, drained to JS)
     emit_normalized: bool,
     normalized: Vec<u8>,
@@ -49,27 +100,240 @@ pub struct ValidatorEngine {
 
     // Per-record reusable starts buffer: starts[i] is start offset for field i
     starts: Vec<usize>,
+
+    // Uniqueness tracking: (schema_col, seen keys) per `unique` column,
+    // plus the composite primary key (schema cols in key order)
+    unique_sets: Vec<(usize, KeySet)>,
+    pk_cols: Vec<usize>,
+    pk_set: Option<KeySet>,
+    key_buf: Vec<u8>,
+
+    // delimiter "auto": input buffered until the dialect is sniffed
+    sniff_buf: Option<Vec<u8>>,
+    sniffed: bool,
+
+    // Non-UTF-8 input: decoder plus reused UTF-8 output buffer
+    transcoder: Option<Transcoder>,
+    decoded: Vec<u8>,
+
+    // Reference sets for `lookup` columns, loaded by the host
+    lookups: LookupSets,
+
+    // Malformed-CSV detection alongside csv-core, plus reused report buffer
+    structure: Scanner,
+    problems: Vec<Problem>,
+
+    // Leading input still to drop: physical lines (skipRows), then whole
+    // records ahead of the header (headerRow)
+    lines_to_skip: usize,
+    records_before_header: usize,
+    header_line: Option<u64>,
 }
 
 #[wasm_bindgen]
 impl ValidatorEngine {
     #[wasm_bindgen(constructor)]
+    /// `now_ms` (Unix epoch milliseconds) is the reference for relative
+    /// `minDate`/`maxDate` bounds such as "today" or "-90d".
     pub fn new(
         schema_json: &str,
         max_errors: u32,
         emit_normalized: bool,
+        now_ms: Option<f64>,
     ) -> Result<ValidatorEngine, JsValue> {
         #[cfg(feature = "dev")]
         console_error_panic_hook::set_once();
 
-        let schema: Schema = serde_json::from_str(schema_json)
-            .map_err(|e| JsValue::from_str(&format!("Invalid schema JSON: {e}")))?;
+        Self::build(schema_json, max_errors, emit_normalized, now_ms)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Load lookup set `name` from a CSV or newline-separated list, streamed
+    /// in chunks (final_chunk=true on the last). Only the first field of each
+    /// record is used, and a header line counts as a value like any other.
+    /// Values add to whatever the set already holds.
+    pub fn push_lookup_chunk(&mut self, name: &str, chunk: &[u8], final_chunk: bool) {
+        self.lookups.push_chunk(name, chunk, final_chunk);
+    }
+
+    /// Add values to lookup set `name` in one call: `data` holds the UTF-8
+    /// values back to back and `ends[i]` is the end offset of value `i`.
+    pub fn push_lookup_packed(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        ends: &[u32],
+    ) -> Result<(), JsValue> {
+        self.lookups
+            .extend_packed(name, data, ends)
+            .map_err(|e| JsValue::from_str(&format!("Invalid lookup data: {e}")))
+    }
+
+    /// Push a CSV chunk into the parser.
+    /// Call with final_chunk=true on the last call (it will flush).
+    pub fn push_chunk(&mut self, chunk: &[u8], final_chunk: bool) -> JsValue {
+        let prog = self.push_bytes(chunk, final_chunk);
+        JsValue::from_str(&serde_json::to_string(&prog).unwrap())
+    }
+
+    /// Validate a whole XLSX workbook (the selected sheet, see `schema.sheet`).
+    /// Rows go through the same header/field/normalization path as CSV records.
+    /// The whole sheet's normalized output is kept until `take_normalized`.
+    /// Errors carry the sheet row number, as Excel shows it.
+    pub fn push_xlsx(&mut self, workbook: &[u8]) -> Result<JsValue, JsValue> {
+        let prog = self
+            .push_workbook(workbook)
+            .map_err(|e| JsValue::from_str(&format!("Invalid XLSX workbook: {e}")))?;
+        Ok(JsValue::from_str(&serde_json::to_string(&prog).unwrap()))
+    }
+
+    /// Drain up to `max` packed errors.
+    /// Each error is 3 u32 words: [row, (kind<<31) | (col<<8) | code, detail].
+    /// `row` is the physical line the record starts on (the sheet row for
+    /// XLSX), so blank, skipped and multi-line records are accounted for;
+    /// 0 means header-level.
+    pub fn take_errors_packed(&mut self, max: u32) -> Vec<u32> {
+        let n = (max as usize).min(self.errors.len());
+        let mut out = Vec::with_capacity(n * 3);
+        for e in self.errors.drain(0..n) {
+            out.extend_from_slice(&e.to_words());
+        }
+        out
+    }
+
+    /// Also record a detail entry for every error from now on (drained with
+    /// `take_error_details_json`). Values are cut to `max_value_len` chars.
+    pub fn enable_error_details(&mut self, max_value_len: u32) {
+        self.detail_value_limit = Some(max_value_len as usize);
+    }
+
+    /// Drain up to `max` error details, as JSON array of
+    /// {row, col, kind, code, value, valueTruncated, line, byteOffset, expected}.
+    /// Entries come in the same order as the packed errors. `byteOffset` is
+    /// into the UTF-8 input as parsed (after transcoding).
+    pub fn take_error_details_json(&mut self, max: u32) -> String {
+        let n = (max as usize).min(self.error_details.len());
+        let drained: Vec<ErrorDetail> = self.error_details.drain(0..n).collect();
+        serde_json::to_string(&drained).unwrap()
+    }
+
+    /// Rule messages (or expressions) in schema order, as JSON array.
+    /// A RuleViolation's `detail` indexes into it.
+    pub fn rule_messages_json(&self) -> String {
+        let messages: Vec<&str> = self.schema.rules.iter().map(|r| r.message()).collect();
+        serde_json::to_string(&messages).unwrap()
+    }
+
+    /// Schema column names in schema order, as JSON array.
+    pub fn schema_columns_json(&self) -> String {
+        serde_json::to_string(&self.schema_col_names).unwrap()
+    }
+
+    /// Input (CSV) header column names in input order, as JSON array.
+    /// Empty array if schema.has_headers=false or header not parsed yet.
+    pub fn input_columns_json(&self) -> String {
+        serde_json::to_string(&self.input_header_names).unwrap()
+    }
+
+    /// Header resolution in input order, as JSON array of
+    /// {inputIndex, input, schemaIndex, column, matchedBy}.
+    /// Empty array if schema.has_headers=false or header not parsed yet.
+    pub fn header_mapping_json(&self) -> String {
+        serde_json::to_string(&self.header_matches).unwrap()
+    }
+
+    /// Dialect in effect, as JSON:
+    /// {encoding, delimiter, quote, hasHeaders, sniffed, headerLine}.
+    /// With delimiter "auto", `delimiter` is null until the first line arrives.
+    pub fn dialect_json(&self) -> String {
+        let pending = self.sniff_buf.is_some();
+        let encoding = match &self.transcoder {
+            Some(t) => t.name(),
+            None => Some("utf-8"),
+        };
+        let info = DialectInfo {
+            encoding: encoding.map(str::to_string),
+            delimiter: (!pending).then(|| (self.schema.delimiter_byte() as char).to_string()),
+            quote: (self.schema.quote as char).to_string(),
+            has_headers: self.schema.has_headers(),
+            sniffed: self.sniffed,
+            header_line: self.header_line,
+        };
+        serde_json::to_string(&info).unwrap()
+    }
+
+    /// Drain normalized CSV bytes accumulated so far (if enabled).
+    pub fn take_normalized(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.normalized)
+    }
+
+    /// Stable string mapping for error code (optional helper).
+    pub fn error_code_to_string(code: u8) -> String {
+        ErrorCode::from_u8(code)
+            .map(|c| c.as_str())
+            .unwrap_or("Unknown")
+            .to_string()
+    }
+}
+
+impl ValidatorEngine {
+    fn build(
+        schema_json: &str,
+        max_errors: u32,
+        emit_normalized: bool,
+        now_ms: Option<f64>,
+    ) -> Result<ValidatorEngine, String> {
+        let mut schema: Schema =
+            serde_json::from_str(schema_json).map_err(|e| format!("Invalid schema JSON: {e}"))?;
+
+        match (schema.header_row, schema.has_headers) {
+            (Some(0), _) => {
+                return Err(
+                    "Invalid schema: headerRow is 1-based (the first record is 1)".to_string(),
+                )
+            }
+            (Some(_), Some(false)) => {
+                return Err(
+                    "Invalid schema: headerRow cannot be used with hasHeaders: false".to_string(),
+                )
+            }
+            (Some(_), None) => schema.has_headers = Some(true),
+            _ => {}
+        }
+        let lines_to_skip = schema.skip_rows;
+        let records_before_header = schema.header_row.map_or(0, |r| r - 1);
+
+        let now = match now_ms {
+            Some(ms) => Some(
+                time::OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000)
+                    .map_err(|e| format!("Invalid now: {e}"))?,
+            ),
+            None => None,
+        };
 
-        let mut rb = ReaderBuilder::new();
-        rb.delimiter(schema.delimiter);
-        rb.terminator(Terminator::CRLF);
-        let rdr = rb.build();
+        let compiled =
+            column::compile_columns(&schema, now).map_err(|e| format!("Invalid schema: {e}"))?;
 
+        header::check_names(&schema).map_err(|e| format!("Invalid schema: {e}"))?;
+
+        let rules = rules::compile_rules(&schema).map_err(|e| format!("Invalid schema: {e}"))?;
+
+        let pk_cols =
+            unique::resolve_primary_key(&schema).map_err(|e| format!("Invalid schema: {e}"))?;
+        let pk_set = (!pk_cols.is_empty()).then(|| KeySet::new(schema.unique_mode));
+        let unique_sets = schema
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.unique)
+            .map(|(i, _)| (i, KeySet::new(schema.unique_mode)))
+            .collect::<Vec<_>>();
+
+        let rdr = build_reader(&schema);
+        let sniff_buf = (schema.delimiter == Delimiter::Auto).then(Vec::new);
+        let schema_encoding = schema.encoding;
+
+        let cols_len = schema.columns.len();
         let schema_col_names = schema
             .columns
             .iter()
@@ -77,8 +341,8 @@ impl ValidatorEngine {
             .collect::<Vec<_>>();
 
         // If no headers, we consider header already "parsed" and schema_to_input is identity
-        let header_parsed = !schema.has_headers;
-        let schema_to_input = if schema.has_headers {
+        let header_parsed = !schema.has_headers();
+        let schema_to_input = if schema.has_headers() {
             Vec::new()
         } else {
             (0..schema.columns.len()).map(Some).collect::<Vec<_>>()
@@ -87,103 +351,141 @@ impl ValidatorEngine {
         Ok(ValidatorEngine {
             schema,
             rdr,
+            compiled,
             out: vec![0u8; 64 * 1024],
             ends: vec![0usize; 256],
+            out_len: 0,
+            ends_len: 0,
+            fed: Vec::new(),
             header_parsed,
             input_to_schema: Vec::new(),
             schema_to_input,
             schema_col_names,
             input_header_names: Vec::new(),
+            header_matches: Vec::new(),
             data_row: 0,
+            row: 0,
+            sheet_row: None,
             errors: Vec::new(),
             max_errors,
+            rules,
+            rule_values: vec![rules::Value::Null; cols_len],
+            row_invalid: vec![false; cols_len],
+            detail_value_limit: None,
+            error_details: Vec::new(),
+            locator: Locator::default(),
             emit_normalized,
             normalized: Vec::with_capacity(256 * 1024),
             normalized_buf_limit: 2 * 1024 * 1024, // drain frequently
             starts: Vec::with_capacity(256),
+            unique_sets,
+            pk_cols,
+            pk_set,
+            key_buf: Vec::new(),
+            sniff_buf,
+            sniffed: false,
+            transcoder: Transcoder::new(schema_encoding),
+            decoded: Vec::new(),
+            lookups: LookupSets::default(),
+            structure: Scanner::default(),
+            problems: Vec::new(),
+            lines_to_skip,
+            records_before_header,
+            header_line: None,
         })
     }
 
-    /// Push a CSV chunk into the parser.
-    /// Call with final_chunk=true on the last call (it will flush).
-    pub fn push_chunk(&mut self, chunk: &[u8], final_chunk: bool) -> JsValue {
+    fn push_bytes(&mut self, chunk: &[u8], final_chunk: bool) -> Progress {
         let before_errs = self.errors.len() as u32;
         let before_rows = self.data_row;
 
-        if !chunk.is_empty() {
-            self.parse_slice(chunk);
+        match self.transcoder.as_mut() {
+            Some(t) => {
+                let mut decoded = std::mem::take(&mut self.decoded);
+                t.transcode(chunk, final_chunk, &mut decoded);
+                self.feed(&decoded, final_chunk);
+                self.decoded = decoded;
+            }
+            None => self.feed(chunk, final_chunk),
         }
 
         if final_chunk {
             self.flush_end();
         }
 
-        let prog = Progress {
+        Progress {
             rows_processed: self.data_row - before_rows,
             errors_added: (self.errors.len() as u32).saturating_sub(before_errs),
             done: final_chunk,
-        };
-
-        JsValue::from_str(&serde_json::to_string(&prog).unwrap())
-    }
-
-    /// Drain up to `max` packed errors.
-    /// Each error is 2 u32 words: [row, (kind<<31) | (col<<8) | code].
-    pub fn take_errors_packed(&mut self, max: u32) -> Vec<u32> {
-        let n = (max as usize).min(self.errors.len());
-        let mut out = Vec::with_capacity(n * 2);
-        for e in self.errors.drain(0..n) {
-            let [w0, w1] = e.to_words();
-            out.push(w0);
-            out.push(w1);
         }
-        out
     }
 
-    /// Schema column names in schema order, as JSON array.
-    pub fn schema_columns_json(&self) -> String {
-        serde_json::to_string(&self.schema_col_names).unwrap()
-    }
+    fn push_workbook(&mut self, workbook: &[u8]) -> Result<Progress, String> {
+        let before_errs = self.errors.len() as u32;
+        let before_rows = self.data_row;
 
-    /// Input (CSV) header column names in input order, as JSON array.
-    /// Empty array if schema.has_headers=false or header not parsed yet.
-    pub fn input_columns_json(&self) -> String {
-        serde_json::to_string(&self.input_header_names).unwrap()
-    }
+        // The host can only drain after the call returns, so the sheet's
+        // normalized rows must not be capped at the streaming buffer limit
+        let limit = std::mem::replace(&mut self.normalized_buf_limit, usize::MAX);
+        let parsed = self.parse_xlsx(workbook);
+        self.normalized_buf_limit = limit;
+        parsed?;
 
-    /// Drain normalized CSV bytes accumulated so far (if enabled).
-    pub fn take_normalized(&mut self) -> Vec<u8> {
-        std::mem::take(&mut self.normalized)
+        Ok(Progress {
+            rows_processed: self.data_row - before_rows,
+            errors_added: (self.errors.len() as u32).saturating_sub(before_errs),
+            done: true,
+        })
     }
 
-    /// Stable string mapping for error code (optional helper).
-    pub fn error_code_to_string(code: u8) -> String {
-        match code {
-            1 => "MissingRequired",
-            2 => "InvalidType",
-            3 => "MaxLengthExceeded",
-            4 => "NotAllowed",
-            5 => "InvalidUtf8",
-            6 => "MissingRequiredColumn",
-            7 => "ExtraColumn",
-            _ => "Unknown",
-        }
-        .to_string()
-    }
-}
+    fn parse_slice(&mut self, input: &[u8]) {
+        // With comments on, csv-core reads a copy with the comment lines
+        // blanked out (see Scanner::blank_comments)
+        let mut fed = std::mem::take(&mut self.fed);
+        let rewrite = self.schema.comment.is_some();
+        if rewrite {
+            fed.clear();
+            fed.extend_from_slice(input);
+        }
 
-impl ValidatorEngine {
-    fn parse_slice(&mut self, mut input: &[u8]) {
-        while !input.is_empty() {
-            let (res, nin, nout, nends) =
-                self.rdr.read_record(input, &mut self.out, &mut self.ends);
-            input = &input[nin..];
+        let mut pos = 0;
+        while pos < input.len() {
+            let rest = &input[pos..];
+            let track = self.detail_value_limit.is_some();
+            if track {
+                self.locator.scan(rest, &self.schema);
+            }
+
+            let ahead = if rewrite {
+                self.structure.blank_comments(&mut fed[pos..], &self.schema);
+                &fed[pos..]
+            } else {
+                rest
+            };
+            let (res, nin, nout, nends) = self.rdr.read_record(
+                ahead,
+                &mut self.out[self.out_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            if track {
+                self.locator.advance(&rest[..nin], &self.schema);
+            }
+            self.structure
+                .scan(&rest[..nin], &self.schema, &mut self.problems);
+            if !self.problems.is_empty() {
+                self.report_problems();
+            }
+            pos += nin;
+            self.out_len += nout;
+            self.ends_len += nends;
 
             match res {
                 ReadRecordResult::Record => {
+                    self.locator.end_record();
+
                     // Avoid borrowing self.out/self.ends across &mut self call:
-                    let nout_local = nout;
-                    let nends_local = nends;
+                    let nout_local = std::mem::take(&mut self.out_len);
+                    let nends_local = std::mem::take(&mut self.ends_len);
 
                     let out_buf = std::mem::take(&mut self.out);
                     let ends_buf = std::mem::take(&mut self.ends);
@@ -194,12 +496,12 @@ impl ValidatorEngine {
                     self.ends = ends_buf;
 
                     if self.hit_error_limit() {
-                        return;
+                        break;
                     }
                 }
                 ReadRecordResult::InputEmpty => {
                     // Need more bytes; just return and continue next chunk (reader keeps state)
-                    return;
+                    break;
                 }
                 ReadRecordResult::OutputFull => {
                     self.out.resize(self.out.len() * 2, 0);
@@ -207,19 +509,82 @@ impl ValidatorEngine {
                 ReadRecordResult::OutputEndsFull => {
                     self.ends.resize(self.ends.len() * 2, 0);
                 }
-                ReadRecordResult::End => return,
+                ReadRecordResult::End => break,
             }
         }
+        self.fed = fed;
+    }
+
+    // UTF-8 input -> dialect sniffing (delimiter "auto") -> csv-core
+    fn feed(&mut self, chunk: &[u8], final_chunk: bool) {
+        let chunk = if self.lines_to_skip > 0 {
+            self.skip_lines(chunk)
+        } else {
+            chunk
+        };
+        if let Some(mut buf) = self.sniff_buf.take() {
+            buf.extend_from_slice(chunk);
+            // Sniff once at least one full (non-blank) line is available (or
+            // input ended)
+            let is_eol = |b: &u8| *b == b'\n' || *b == b'\r';
+            if final_chunk || buf.iter().skip_while(|b| is_eol(b)).any(is_eol) {
+                self.apply_dialect(sniff::sniff(&buf, final_chunk, &self.schema));
+                self.parse_slice(&buf);
+            } else {
+                self.sniff_buf = Some(buf);
+            }
+        } else if !chunk.is_empty() {
+            self.parse_slice(chunk);
+        }
+    }
+
+    /// Drop leading lines for `skipRows`, keeping byte offsets and line
+    /// numbers in step with the file. Returns what is left of `chunk`.
+    fn skip_lines<'a>(&mut self, chunk: &'a [u8]) -> &'a [u8] {
+        let cut = self
+            .structure
+            .skip_lines(chunk, &mut self.lines_to_skip, &self.schema);
+        let (dropped, rest) = chunk.split_at(cut);
+        if self.detail_value_limit.is_some() {
+            self.locator.advance(dropped, &self.schema);
+        }
+        rest
+    }
+
+    fn apply_dialect(&mut self, d: sniff::Sniffed) {
+        self.schema.delimiter = Delimiter::Byte(d.delimiter);
+        self.schema.quote = d.quote;
+        self.schema.has_headers = Some(d.has_headers);
+        self.sniffed = true;
+
+        self.rdr = build_reader(&self.schema);
+        self.header_parsed = !d.has_headers;
+        self.schema_to_input = if d.has_headers {
+            Vec::new()
+        } else {
+            (0..self.schema.columns.len()).map(Some).collect()
+        };
     }
 
     fn flush_end(&mut self) {
+        if let Some(p) = self.structure.finish() {
+            self.problems.push(p);
+            self.report_problems();
+        }
+
         loop {
-            let (res, _nin, nout, nends) = self.rdr.read_record(&[], &mut self.out, &mut self.ends);
+            let (res, _nin, nout, nends) = self.rdr.read_record(
+                &[],
+                &mut self.out[self.out_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            self.out_len += nout;
+            self.ends_len += nends;
 
             match res {
                 ReadRecordResult::Record => {
-                    let nout_local = nout;
-                    let nends_local = nends;
+                    let nout_local = std::mem::take(&mut self.out_len);
+                    let nends_local = std::mem::take(&mut self.ends_len);
 
                     let out_buf = std::mem::take(&mut self.out);
                     let ends_buf = std::mem::take(&mut self.ends);
@@ -245,18 +610,175 @@ impl ValidatorEngine {
         }
     }
 
+    /// Structural problems are input-kind errors on the record being read
+    /// (row 0 while in the header): col = field index, detail = byte offset.
+    fn report_problems(&mut self) {
+        let mut problems = std::mem::take(&mut self.problems);
+        for p in problems.drain(..) {
+            let row = if self.header_parsed {
+                u32::try_from(p.line).unwrap_or(u32::MAX)
+            } else {
+                0
+            };
+            let code = match p.kind {
+                ProblemKind::UnexpectedQuote => ErrorCode::UnexpectedQuote,
+                ProblemKind::UnterminatedQuote => ErrorCode::UnterminatedQuote,
+                ProblemKind::FieldTooLarge => ErrorCode::FieldTooLarge,
+                ProblemKind::RecordTooLarge => ErrorCode::RecordTooLarge,
+            };
+            let offset = u32::try_from(p.offset).unwrap_or(u32::MAX);
+            self.push_err_detail(row, p.field as u32, code, ColKind::Input, offset);
+        }
+        self.problems = problems;
+    }
+
+    fn parse_xlsx(&mut self, workbook: &[u8]) -> Result<(), String> {
+        let sheet = self.schema.sheet.clone();
+        self.locator.detach();
+
+        // Reused per-row buffers, same shape csv-core produces
+        let mut record: Vec<u8> = Vec::with_capacity(4 * 1024);
+        let mut ends: Vec<usize> = Vec::with_capacity(256);
+
+        xlsx::read_rows(workbook, sheet.as_ref(), |sheet_row, cells, info| {
+            // skipRows counts sheet rows, empty ones included
+            if (sheet_row as usize) <= self.schema.skip_rows {
+                return true;
+            }
+            record.clear();
+            ends.clear();
+
+            for cell in cells {
+                // Sparse rows: missing cells in between are empty fields
+                while ends.len() < cell.col {
+                    ends.push(record.len());
+                }
+                match self.xlsx_date_text(cell, info) {
+                    Some(date) => record.extend_from_slice(date.as_bytes()),
+                    None => record.extend_from_slice(cell.value.as_bytes()),
+                }
+                ends.push(record.len());
+            }
+            // Trailing empty cells are not stored; a sheet row is never short
+            if self.header_parsed {
+                while ends.len() < self.row_width() {
+                    ends.push(record.len());
+                }
+            }
+
+            self.sheet_row = Some(sheet_row);
+            self.handle_record(&record, &ends);
+            !self.hit_error_limit()
+        })?;
+        self.sheet_row = None;
+
+        Ok(())
+    }
+
+    /// Numeric cells feeding a date/datetime/time column hold Excel serials;
+    /// render them as text the column accepts so `validate_field` sees a
+    /// normal value (datetimes/times naive, i.e. in the column's timezone).
+    fn xlsx_date_text(&self, cell: &xlsx::Cell, info: xlsx::WorkbookInfo) -> Option<String> {
+        if !cell.numeric || !self.header_parsed {
+            return None;
+        }
+
+        let schema_idx = if self.schema.has_headers() {
+            self.input_to_schema.get(cell.col).copied().flatten()?
+        } else if cell.col < self.schema.columns.len() {
+            cell.col
+        } else {
+            return None;
+        };
+
+        let col = &self.schema.columns[schema_idx];
+        let serial: f64 = match col.col_type {
+            ColumnType::Date | ColumnType::Datetime | ColumnType::Time => {
+                cell.value.trim().parse().ok()?
+            }
+            _ => return None,
+        };
+
+        match col.col_type {
+            ColumnType::Datetime => {
+                let dt = xlsx::serial_to_datetime(serial, info.date1904)?;
+                Some(format!(
+                    "{}T{:02}:{:02}:{:02}",
+                    format_date(dt.date(), DateFormat::YmdDash),
+                    dt.hour(),
+                    dt.minute(),
+                    dt.second()
+                ))
+            }
+            ColumnType::Time => {
+                let t = xlsx::serial_to_time(serial)?;
+                Some(format!(
+                    "{:02}:{:02}:{:02}",
+                    t.hour(),
+                    t.minute(),
+                    t.second()
+                ))
+            }
+            _ => {
+                let date = xlsx::serial_to_date(serial, info.date1904)?;
+                self.compiled[schema_idx].date_formats.first()?.format(date)
+            }
+        }
+    }
+
     fn handle_record(&mut self, record: &[u8], ends: &[usize]) {
-        if self.schema.has_headers && !self.header_parsed {
+        // Fields are back to back in `record`, so this means every field is blank
+        let blank = self.schema.skip_blank_lines && record.iter().all(u8::is_ascii_whitespace);
+
+        if self.schema.has_headers() && !self.header_parsed {
+            if blank {
+                return;
+            }
+            if self.records_before_header > 0 {
+                self.records_before_header -= 1;
+                return;
+            }
+            self.header_line = self.record_line();
             self.parse_header(record, ends);
             self.header_parsed = true;
             return;
         }
 
+        if blank {
+            return;
+        }
+
         // Data row
         self.data_row = self.data_row.saturating_add(1);
+        self.row = self
+            .record_line()
+            .map_or(self.data_row, |l| u32::try_from(l).unwrap_or(u32::MAX));
+
+        // Arity: col = first missing / extra input position, detail = expected width
+        let width = self.row_width();
+        let policy = self.schema.ragged_rows;
+        let arity_err = match (ends.len().cmp(&width), policy) {
+            (Ordering::Less, RaggedRows::Reject | RaggedRows::Truncate) => {
+                Some((ErrorCode::RowTooShort, ends.len()))
+            }
+            (Ordering::Greater, RaggedRows::Reject | RaggedRows::Pad) => {
+                Some((ErrorCode::RowTooLong, width))
+            }
+            _ => None,
+        };
+        if let Some((code, col)) = arity_err {
+            let detail = width as u32;
+            self.push_err_detail(self.row, col as u32, code, ColKind::Input, detail);
+            return;
+        }
+        let ends = match policy {
+            RaggedRows::Truncate if ends.len() > width => &ends[..width],
+            _ => ends,
+        };
 
         self.starts.clear();
         self.starts.reserve(ends.len());
+        self.row_invalid.fill(false);
 
         let mut start = 0usize;
 
@@ -265,7 +787,7 @@ impl ValidatorEngine {
             let field = &record[start..end];
             start = end;
 
-            let schema_idx_opt = if self.schema.has_headers {
+            let schema_idx_opt = if self.schema.has_headers() {
                 self.input_to_schema.get(input_col).copied().flatten()
             } else if input_col < self.schema.columns.len() {
                 Some(input_col)
@@ -273,44 +795,66 @@ impl ValidatorEngine {
                 None
             };
 
+            let details_before = self.error_details.len();
             if let Some(schema_idx) = schema_idx_opt {
+                let errors_before = self.errors.len();
                 self.validate_field(schema_idx, field);
+                self.row_invalid[schema_idx] |= self.errors.len() > errors_before;
             } else if self.schema.fail_on_extra_columns {
                 // input column index in `col`, mark as input-kind
                 self.push_err(
-                    self.data_row,
+                    self.row,
                     input_col as u32,
                     ErrorCode::ExtraColumn,
                     ColKind::Input,
                 );
             }
+            self.attach_value(details_before, field);
 
             if self.hit_error_limit() {
                 return;
             }
         }
 
-        // Missing required fields if row shorter than schema (no headers case)
-        if !self.schema.has_headers {
-            let cols_len = self.schema.columns.len();
-            if ends.len() < cols_len {
-                for schema_idx in ends.len()..cols_len {
-                    if self.schema.columns[schema_idx].required {
-                        self.push_err(
-                            self.data_row,
-                            schema_idx as u32,
-                            ErrorCode::MissingRequired,
-                            ColKind::Schema,
-                        );
-                        if self.hit_error_limit() {
-                            return;
-                        }
-                    }
+        // Required columns a short row does not reach (rows that fail the
+        // arity check never get here)
+        for schema_idx in 0..self.schema.columns.len() {
+            let input_i = if self.schema.has_headers() {
+                self.schema_to_input.get(schema_idx).copied().flatten()
+            } else {
+                Some(schema_idx)
+            };
+            let missing = input_i.is_some_and(|i| i >= ends.len());
+            if missing && self.schema.columns[schema_idx].required {
+                self.push_err(
+                    self.row,
+                    schema_idx as u32,
+                    ErrorCode::MissingRequired,
+                    ColKind::Schema,
+                );
+                self.row_invalid[schema_idx] = true;
+                if self.hit_error_limit() {
+                    return;
                 }
             }
         }
 
-        // Emit normalized row (optional)
+        self.check_conditions(record, ends);
+        if self.hit_error_limit() {
+            return;
+        }
+
+        self.check_rules(record, ends);
+        if self.hit_error_limit() {
+            return;
+        }
+
+        self.check_uniqueness(record, ends);
+        if self.hit_error_limit() {
+            return;
+        }
+
+        // Emit normalized row (optional); rows are written whole or not at all
         if self.emit_normalized && self.normalized.len() < self.normalized_buf_limit {
             self.write_normalized_row(record, ends);
         }
@@ -333,35 +877,69 @@ impl ValidatorEngine {
                 Ok(s) => s.to_string(),
                 Err(_) => {
                     // header-level: invalid utf8 (no useful col index, keep schema-kind)
+                    let details_before = self.error_details.len();
                     self.push_err(0, 0, ErrorCode::InvalidUtf8, ColKind::Schema);
-                    continue;
+                    self.attach_value(details_before, field);
+                    // keep input indices aligned with the record
+                    String::from_utf8_lossy(trim_ascii(field)).into_owned()
                 }
             };
             input_names.push(name);
         }
 
-        // Store input header names for JS/UI
-        self.input_header_names = input_names.clone();
-
-        // Build mappings
+        // Build mappings (see header::resolve for precedence)
+        let resolved = header::resolve(&input_names, &self.schema);
         self.input_to_schema = vec![None; input_names.len()];
         self.schema_to_input = vec![None; self.schema.columns.len()];
+        self.header_matches = Vec::with_capacity(input_names.len());
 
         for (input_i, nm) in input_names.iter().enumerate() {
-            if let Some(schema_idx) = self.schema.columns.iter().position(|c| c.name == *nm) {
+            let matched = resolved[input_i];
+            if let Some((schema_idx, _)) = matched {
                 self.input_to_schema[input_i] = Some(schema_idx);
-                self.schema_to_input[schema_idx] = Some(input_i);
+                // duplicateHeaders "all": the first copy represents the column
+                self.schema_to_input[schema_idx].get_or_insert(input_i);
+            }
+            self.header_matches.push(HeaderMatch {
+                input_index: input_i,
+                input: nm.clone(),
+                schema_index: matched.map(|(i, _)| i),
+                column: matched.map(|(i, _)| self.schema.columns[i].name.clone()),
+                matched_by: matched.map(|(_, by)| match by {
+                    MatchedBy::Name => "name",
+                    MatchedBy::Alias => "alias",
+                }),
+            });
+        }
+
+        // Blank and repeated names (input-kind; DuplicateHeader's detail is
+        // the input index of the first occurrence)
+        let duplicates = header::duplicates(&input_names, self.schema.header_matching);
+        for (input_i, nm) in input_names.iter().enumerate() {
+            let found = match duplicates[input_i] {
+                _ if nm.is_empty() => Some((ErrorCode::EmptyHeader, 0)),
+                Some(first) => Some((ErrorCode::DuplicateHeader, first as u32)),
+                None => None,
+            };
+            if let Some((code, detail)) = found {
+                let details_before = self.error_details.len();
+                self.push_err_detail(0, input_i as u32, code, ColKind::Input, detail);
+                self.attach_value(details_before, nm.as_bytes());
             }
         }
 
-        // Ensure required columns exist
+        // Store input header names for JS/UI
+        self.input_header_names = input_names;
+
+        self.check_column_order();
+
+        // Ensure required columns exist (all of them under a positional
+        // columnOrder, where a missing column shifts the ones after it)
+        let positional = self.schema.column_order != ColumnOrder::Any;
         let mut missing: Vec<usize> = Vec::new();
         for (schema_idx, col) in self.schema.columns.iter().enumerate() {
-            if col.required {
-                let exists = input_names.iter().any(|n| n == &col.name);
-                if !exists {
-                    missing.push(schema_idx);
-                }
+            if (col.required || positional) && self.schema_to_input[schema_idx].is_none() {
+                missing.push(schema_idx);
             }
         }
         for schema_idx in missing {
@@ -374,13 +952,43 @@ impl ValidatorEngine {
         }
     }
 
+    /// `columnOrder`: schema column k must be input column k. Out-of-place
+    /// columns get ColumnOutOfOrder (col = expected position as schema index,
+    /// detail = actual input index); in strict mode unmatched input columns
+    /// are ExtraColumn errors on the header. Absent schema columns are
+    /// reported as missing by `parse_header`.
+    fn check_column_order(&mut self) {
+        if self.schema.column_order == ColumnOrder::Any {
+            return;
+        }
+        for schema_idx in 0..self.schema.columns.len() {
+            match self.schema_to_input[schema_idx] {
+                Some(input_i) if input_i != schema_idx => self.push_err_detail(
+                    0,
+                    schema_idx as u32,
+                    ErrorCode::ColumnOutOfOrder,
+                    ColKind::Schema,
+                    input_i as u32,
+                ),
+                _ => {}
+            }
+        }
+        if self.schema.column_order == ColumnOrder::Strict {
+            for input_i in 0..self.input_to_schema.len() {
+                if self.input_to_schema[input_i].is_none() {
+                    self.push_err(0, input_i as u32, ErrorCode::ExtraColumn, ColKind::Input);
+                }
+            }
+        }
+    }
+
     fn validate_field(&mut self, schema_idx: usize, raw: &[u8]) {
         let trimmed = trim_ascii(raw);
 
         let required = self.schema.columns[schema_idx].required;
         if required && trimmed.is_empty() {
             self.push_err(
-                self.data_row,
+                self.row,
                 schema_idx as u32,
                 ErrorCode::MissingRequired,
                 ColKind::Schema,
@@ -388,24 +996,20 @@ impl ValidatorEngine {
             return;
         }
 
-        let max_len = self.schema.columns[schema_idx].max_len;
-        if let Some(max_len) = max_len {
-            if trimmed.len() > max_len {
-                self.push_err(
-                    self.data_row,
-                    schema_idx as u32,
-                    ErrorCode::MaxLengthExceeded,
-                    ColKind::Schema,
-                );
-                return;
-            }
+        // Conditionally required: an empty value is judged once the whole
+        // row is known (check_conditions), not by the type checks below
+        let compiled = &self.compiled[schema_idx];
+        if trimmed.is_empty()
+            && (compiled.required_if.is_some() || compiled.required_unless.is_some())
+        {
+            return;
         }
 
         let s = match str::from_utf8(trimmed) {
             Ok(v) => v,
             Err(_) => {
                 self.push_err(
-                    self.data_row,
+                    self.row,
                     schema_idx as u32,
                     ErrorCode::InvalidUtf8,
                     ColKind::Schema,
@@ -414,52 +1018,209 @@ impl ValidatorEngine {
             }
         };
 
-        // Allowed values check (scoped borrow)
-        let not_allowed = {
-            let allowed = &self.schema.columns[schema_idx].allowed;
-            !allowed.is_empty() && !allowed.iter().any(|a| a == s)
+        // Length (detail = measured length) and character classes
+        // (detail = the offending code point)
+        let col = &self.schema.columns[schema_idx];
+        let len = match (col.max_len, col.min_len, col.exact_len) {
+            (None, None, None) => 0,
+            _ => col.length_unit.count(s),
         };
-        if not_allowed {
-            self.push_err(
-                self.data_row,
+        let length_err = if col.max_len.is_some_and(|max| len > max) {
+            Some((ErrorCode::MaxLengthExceeded, len))
+        } else if s.is_empty() {
+            None
+        } else if col.min_len.is_some_and(|min| len < min) {
+            Some((ErrorCode::MinLengthNotMet, len))
+        } else if col.exact_len.is_some_and(|exact| len != exact) {
+            Some((ErrorCode::ExactLengthMismatch, len))
+        } else {
+            None
+        };
+        let class_err = match col.char_class.as_slice() {
+            [] => None,
+            classes => s
+                .chars()
+                .find(|&c| !classes.iter().all(|cc| cc.allows(c)))
+                .map(|c| (ErrorCode::DisallowedCharacter, c as usize)),
+        };
+        if let Some((code, detail)) = length_err.or(class_err) {
+            self.push_err_detail(
+                self.row,
                 schema_idx as u32,
-                ErrorCode::NotAllowed,
+                code,
                 ColKind::Schema,
+                detail as u32,
             );
             return;
         }
 
-        let col_type = self.schema.columns[schema_idx].col_type;
-        match col_type {
-            ColumnType::String => {}
-            ColumnType::Int => {
+        // Allowed values; the checks below see the canonical value
+        let canonical;
+        let s = match &self.compiled[schema_idx].allowed {
+            Some(allowed) => match allowed.canonical(s) {
+                Some(v) => {
+                    canonical = v.clone();
+                    &*canonical
+                }
+                None => {
+                    self.push_err(
+                        self.row,
+                        schema_idx as u32,
+                        ErrorCode::NotAllowed,
+                        ColKind::Schema,
+                    );
+                    return;
+                }
+            },
+            None => s,
+        };
+
+        // Empty values are left to `required`
+        let not_in_lookup = match &self.schema.columns[schema_idx].lookup {
+            Some(name) => !trimmed.is_empty() && !self.lookups.contains(name, trimmed),
+            None => false,
+        };
+        if not_in_lookup {
+            self.push_err(
+                self.row,
+                schema_idx as u32,
+                ErrorCode::NotInLookup,
+                ColKind::Schema,
+            );
+            return;
+        }
+
+        let mismatch = self.compiled[schema_idx]
+            .pattern
+            .as_ref()
+            .is_some_and(|re| !re.is_match(s));
+        if mismatch {
+            self.push_err(
+                self.row,
+                schema_idx as u32,
+                ErrorCode::PatternMismatch,
+                ColKind::Schema,
+            );
+            return;
+        }
+
+        let col_type = self.schema.columns[schema_idx].col_type;
+        match col_type {
+            ColumnType::String => {
+                if self.schema.injection_policy == InjectionPolicy::Flag {
+                    // detail = the offending code point
+                    let found = injection::formula_trigger(raw, s)
+                        .map(|c| (ErrorCode::FormulaInjection, c))
+                        .or_else(|| {
+                            injection::control_char(s).map(|c| (ErrorCode::ControlCharacter, c))
+                        });
+                    if let Some((code, c)) = found {
+                        self.push_err_detail(
+                            self.row,
+                            schema_idx as u32,
+                            code,
+                            ColKind::Schema,
+                            c as u32,
+                        );
+                    }
+                }
+            }
+            ColumnType::Int => {
                 if !is_valid_int(s) {
                     self.push_err(
-                        self.data_row,
+                        self.row,
                         schema_idx as u32,
                         ErrorCode::InvalidType,
                         ColKind::Schema,
                     );
+                } else if !in_range(
+                    &self.schema.columns[schema_idx],
+                    Decimal::from_str_exact(s).ok(),
+                ) {
+                    self.push_err(
+                        self.row,
+                        schema_idx as u32,
+                        ErrorCode::OutOfRange,
+                        ColKind::Schema,
+                    );
                 }
             }
-            ColumnType::Decimal => {
-                let precision = self.schema.columns[schema_idx].precision.unwrap_or(2);
-                if !is_valid_decimal(s, precision) {
+            ColumnType::Decimal => match parse_decimal(s) {
+                None => self.push_err(
+                    self.row,
+                    schema_idx as u32,
+                    ErrorCode::InvalidType,
+                    ColKind::Schema,
+                ),
+                Some(d) if !in_range(&self.schema.columns[schema_idx], Some(d)) => self.push_err(
+                    self.row,
+                    schema_idx as u32,
+                    ErrorCode::OutOfRange,
+                    ColKind::Schema,
+                ),
+                Some(d) => {
+                    if let Err(code) = fit_decimal(d, &self.schema.columns[schema_idx]) {
+                        self.push_err(self.row, schema_idx as u32, code, ColKind::Schema);
+                    }
+                }
+            },
+            ColumnType::Date => match self.compiled[schema_idx].parse_date(s) {
+                None => self.push_err(
+                    self.row,
+                    schema_idx as u32,
+                    ErrorCode::InvalidType,
+                    ColKind::Schema,
+                ),
+                Some(d) if !date_in_range(&self.compiled[schema_idx], |b| b.cmp_date(d)) => self
+                    .push_err(
+                        self.row,
+                        schema_idx as u32,
+                        ErrorCode::OutOfRange,
+                        ColKind::Schema,
+                    ),
+                Some(_) => {}
+            },
+            ColumnType::Datetime => {
+                let tz = self.compiled[schema_idx].timezone;
+                match temporal::parse_datetime(s, tz) {
+                    None => self.push_err(
+                        self.row,
+                        schema_idx as u32,
+                        ErrorCode::InvalidType,
+                        ColKind::Schema,
+                    ),
+                    Some(t)
+                        if !date_in_range(&self.compiled[schema_idx], |b| b.cmp_datetime(t)) =>
+                    {
+                        self.push_err(
+                            self.row,
+                            schema_idx as u32,
+                            ErrorCode::OutOfRange,
+                            ColKind::Schema,
+                        )
+                    }
+                    Some(_) => {}
+                }
+            }
+            ColumnType::Time => {
+                let tz = self.compiled[schema_idx].timezone;
+                if temporal::parse_time(s, tz).is_none() {
                     self.push_err(
-                        self.data_row,
+                        self.row,
                         schema_idx as u32,
                         ErrorCode::InvalidType,
                         ColKind::Schema,
                     );
                 }
             }
-            ColumnType::Date => {
-                let fmt = self.schema.columns[schema_idx]
-                    .date_format
-                    .unwrap_or(DateFormat::YmdDash);
-                if !is_valid_date(s, fmt) {
+            ColumnType::Bool => {
+                let valid = self.compiled[schema_idx]
+                    .bools
+                    .as_ref()
+                    .is_some_and(|b| b.parse(s).is_some());
+                if !valid {
                     self.push_err(
-                        self.data_row,
+                        self.row,
                         schema_idx as u32,
                         ErrorCode::InvalidType,
                         ColKind::Schema,
@@ -473,89 +1234,357 @@ impl ValidatorEngine {
         let cols_len = self.schema.columns.len();
 
         for schema_i in 0..cols_len {
-            let col = self.schema.columns[schema_i].clone();
+            let trimmed = trim_ascii(self.schema_field(record, ends, schema_i));
+            let normalized = self.normalize_for_output(schema_i, trimmed);
 
-            let input_i_opt = if self.schema.has_headers {
-                self.schema_to_input.get(schema_i).copied().flatten()
+            self.write_csv_field(&normalized);
+
+            if schema_i + 1 < cols_len {
+                self.normalized.push(self.schema.delimiter_byte());
             } else {
-                Some(schema_i)
-            };
+                self.normalized.push(match self.schema.terminator {
+                    RecordTerminator::Crlf => b'\n',
+                    RecordTerminator::Byte(t) => t,
+                });
+            }
+        }
+    }
 
-            let field_bytes: &[u8] = if let Some(input_i) = input_i_opt {
-                if input_i < ends.len() && input_i < self.starts.len() {
-                    &record[self.starts[input_i]..ends[input_i]]
-                } else {
-                    b""
-                }
+    /// Physical line (CSV) or sheet row (XLSX) of the record being handled.
+    fn record_line(&self) -> Option<u64> {
+        match self.sheet_row {
+            Some(r) => Some(u64::from(r)),
+            None => self.structure.record_line(),
+        }
+    }
+
+    /// Field count a record should have: the header's, or the schema's
+    /// without headers.
+    fn row_width(&self) -> usize {
+        if self.schema.has_headers() {
+            self.input_header_names.len()
+        } else {
+            self.schema.columns.len()
+        }
+    }
+
+    /// Raw bytes of schema column `schema_i` in the current record
+    /// (empty when the column is not mapped or the row is short).
+    fn schema_field<'a>(&self, record: &'a [u8], ends: &[usize], schema_i: usize) -> &'a [u8] {
+        let input_i_opt = if self.schema.has_headers() {
+            self.schema_to_input.get(schema_i).copied().flatten()
+        } else {
+            Some(schema_i)
+        };
+
+        match input_i_opt {
+            Some(input_i) if input_i < ends.len() && input_i < self.starts.len() => {
+                &record[self.starts[input_i]..ends[input_i]]
+            }
+            _ => b"",
+        }
+    }
+
+    /// requiredIf / requiredUnless / forbiddenIf, against the rest of the row.
+    fn check_conditions(&mut self, record: &[u8], ends: &[usize]) {
+        for schema_idx in 0..self.compiled.len() {
+            let compiled = &self.compiled[schema_idx];
+            if compiled.required_if.is_none()
+                && compiled.required_unless.is_none()
+                && compiled.forbidden_if.is_none()
+            {
+                continue;
+            }
+
+            let holds = |c: &column::RowCondition| {
+                c.holds(trim_ascii(self.schema_field(record, ends, c.column)))
+            };
+            let required = compiled.required_if.as_ref().is_some_and(holds)
+                || compiled.required_unless.as_ref().is_some_and(|c| !holds(c));
+            let forbidden = compiled.forbidden_if.as_ref().is_some_and(holds);
+
+            let value = trim_ascii(self.schema_field(record, ends, schema_idx));
+            let code = if value.is_empty() && required && !self.schema.columns[schema_idx].required
+            {
+                ErrorCode::MissingRequired
+            } else if !value.is_empty() && forbidden {
+                ErrorCode::Forbidden
             } else {
-                b""
+                continue;
             };
 
-            let trimmed = trim_ascii(field_bytes);
-            let normalized = self.normalize_for_output(&col, trimmed);
+            let details_before = self.error_details.len();
+            self.push_err(self.row, schema_idx as u32, code, ColKind::Schema);
+            self.attach_value(details_before, value);
+            self.row_invalid[schema_idx] = true;
+            if self.hit_error_limit() {
+                return;
+            }
+        }
+    }
 
-            self.write_csv_field(&normalized);
+    fn check_rules(&mut self, record: &[u8], ends: &[usize]) {
+        if self.rules.is_empty() {
+            return;
+        }
 
-            if schema_i + 1 < cols_len {
-                self.normalized.push(self.schema.delimiter);
-            } else {
-                self.normalized.push(b'\n');
+        let rules = std::mem::take(&mut self.rules);
+        for rule in &rules {
+            for &schema_idx in &rule.columns {
+                let raw = trim_ascii(self.schema_field(record, ends, schema_idx));
+                self.rule_values[schema_idx] = self.typed_value(schema_idx, raw);
+            }
+        }
+
+        for (rule_i, rule) in rules.iter().enumerate() {
+            // Invalid inputs were already reported; the rule can't be judged
+            if rule.columns.iter().any(|&c| self.row_invalid[c]) {
+                continue;
+            }
+            if !rule.holds(&self.rule_values) {
+                let details_before = self.error_details.len();
+                self.push_err_detail(
+                    self.row,
+                    rule.report_col as u32,
+                    ErrorCode::RuleViolation,
+                    ColKind::Schema,
+                    rule_i as u32,
+                );
+                let raw = self.schema_field(record, ends, rule.report_col);
+                self.attach_value(details_before, raw);
             }
+        }
+        self.rules = rules;
+    }
+
+    /// A field as a rule value; empty (or unparsable) fields are null.
+    fn typed_value(&self, schema_idx: usize, trimmed: &[u8]) -> rules::Value {
+        use rules::Value;
+
+        let s = match str::from_utf8(trimmed) {
+            Ok(s) if !s.is_empty() => s,
+            _ => return Value::Null,
+        };
+        let compiled = &self.compiled[schema_idx];
+        let v = match self.schema.columns[schema_idx].col_type {
+            ColumnType::String => Some(Value::Str(s.to_string())),
+            ColumnType::Int => Decimal::from_str_exact(s).ok().map(Value::Num),
+            ColumnType::Decimal => parse_decimal(s).map(Value::Num),
+            ColumnType::Date => compiled.parse_date(s).map(Value::Date),
+            ColumnType::Datetime => {
+                temporal::parse_datetime(s, compiled.timezone).map(Value::DateTime)
+            }
+            ColumnType::Time => temporal::parse_time(s, compiled.timezone).map(Value::Time),
+            ColumnType::Bool => compiled
+                .bools
+                .as_ref()
+                .and_then(|b| b.parse(s))
+                .map(Value::Bool),
+        };
+        v.unwrap_or(Value::Null)
+    }
 
-            if self.normalized.len() >= self.normalized_buf_limit {
+    fn check_uniqueness(&mut self, record: &[u8], ends: &[usize]) {
+        let row = self.row;
+
+        for i in 0..self.unique_sets.len() {
+            let schema_idx = self.unique_sets[i].0;
+            let value = trim_ascii(self.schema_field(record, ends, schema_idx));
+            if value.is_empty() {
+                continue;
+            }
+            let key = self.key_value(schema_idx, value);
+            if let Some(first) = self.unique_sets[i].1.insert(&key, row) {
+                let details_before = self.error_details.len();
+                self.push_err_detail(
+                    row,
+                    schema_idx as u32,
+                    ErrorCode::DuplicateValue,
+                    ColKind::Schema,
+                    first,
+                );
+                self.attach_value(details_before, value);
+            }
+        }
+
+        if self.pk_set.is_none() {
+            return;
+        }
+
+        // Rows with an incomplete key are not tracked
+        let mut key = std::mem::take(&mut self.key_buf);
+        key.clear();
+        let mut complete = true;
+        for &schema_idx in &self.pk_cols {
+            let value = trim_ascii(self.schema_field(record, ends, schema_idx));
+            if value.is_empty() {
+                complete = false;
                 break;
             }
+            unique::push_key_part(&mut key, &self.key_value(schema_idx, value));
         }
+
+        if complete {
+            if let Some(first) = self.pk_set.as_mut().and_then(|set| set.insert(&key, row)) {
+                let details_before = self.error_details.len();
+                self.push_err_detail(
+                    row,
+                    self.pk_cols[0] as u32,
+                    ErrorCode::DuplicateValue,
+                    ColKind::Schema,
+                    first,
+                );
+                if self.detail_value_limit.is_some() {
+                    // Key columns joined like they would appear in the input
+                    let mut joined = Vec::new();
+                    for (i, &schema_idx) in self.pk_cols.iter().enumerate() {
+                        if i > 0 {
+                            joined.push(self.schema.delimiter_byte());
+                        }
+                        joined.extend_from_slice(trim_ascii(
+                            self.schema_field(record, ends, schema_idx),
+                        ));
+                    }
+                    self.attach_value(details_before, &joined);
+                }
+            }
+        }
+        self.key_buf = key;
     }
 
-    fn normalize_for_output(&self, col: &schema::ColumnSpec, trimmed: &[u8]) -> Vec<u8> {
+    /// Form a non-empty field is compared in for `unique` / `primaryKey`: the
+    /// normalized output value (so "1.0" and "1" collide in a decimal
+    /// column), or the trimmed input when the value does not normalize.
+    fn key_value(&self, schema_idx: usize, trimmed: &[u8]) -> Vec<u8> {
+        match self.normalize_for_output(schema_idx, trimmed) {
+            v if v.is_empty() => trimmed.to_vec(),
+            v => v,
+        }
+    }
+
+    fn normalize_for_output(&self, schema_i: usize, trimmed: &[u8]) -> Vec<u8> {
+        let col = &self.schema.columns[schema_i];
         let s = match str::from_utf8(trimmed) {
             Ok(v) => v,
             Err(_) => return Vec::new(),
         };
+        let s = match &self.compiled[schema_i].allowed {
+            Some(allowed) => allowed.canonical(s).map_or(s, |v| &**v),
+            None => s,
+        };
 
         match col.col_type {
-            ColumnType::String => s.as_bytes().to_vec(),
+            ColumnType::String => match self.schema.injection_policy {
+                InjectionPolicy::Neutralize => injection::neutralize(s),
+                _ => s.as_bytes().to_vec(),
+            },
             ColumnType::Int => s.as_bytes().to_vec(),
-            ColumnType::Decimal => {
-                let precision = col.precision.unwrap_or(2);
-                normalize_decimal(s, precision)
-                    .unwrap_or_default()
-                    .into_bytes()
-            }
-            ColumnType::Date => {
-                let fmt = col.date_format.unwrap_or(DateFormat::YmdDash);
-                normalize_date(s, fmt).unwrap_or_default().into_bytes()
-            }
+            ColumnType::Decimal => normalize_decimal(s, col).unwrap_or_default().into_bytes(),
+            ColumnType::Date => self.compiled[schema_i]
+                .parse_date(s)
+                .map(|d| format_date(d, DateFormat::YmdDash))
+                .unwrap_or_default()
+                .into_bytes(),
+            ColumnType::Datetime => temporal::parse_datetime(s, self.compiled[schema_i].timezone)
+                .map(temporal::format_datetime)
+                .unwrap_or_default()
+                .into_bytes(),
+            ColumnType::Time => temporal::parse_time(s, self.compiled[schema_i].timezone)
+                .map(temporal::format_time)
+                .unwrap_or_default()
+                .into_bytes(),
+            ColumnType::Bool => match self.compiled[schema_i]
+                .bools
+                .as_ref()
+                .and_then(|b| b.parse(s))
+            {
+                Some(true) => b"true".to_vec(),
+                Some(false) => b"false".to_vec(),
+                None => Vec::new(),
+            },
         }
     }
 
     fn write_csv_field(&mut self, bytes: &[u8]) {
+        let quote = self.schema.quote;
+        // The reader honours the escape even with double_quote on, so the
+        // escape byte itself always needs escaping
+        let escape = self.schema.escape;
+        let quote_prefix = match escape {
+            Some(e) if !self.schema.double_quote => e,
+            _ => quote,
+        };
+        let terminator = match self.schema.terminator {
+            RecordTerminator::Crlf => None,
+            RecordTerminator::Byte(t) => Some(t),
+        };
+
         let needs_quote = bytes.iter().any(|&b| {
-            b == self.schema.delimiter || b == b'"' || b == b'\n' || b == b'\r'
-        });
+            b == self.schema.delimiter_byte()
+                || b == quote
+                || b == b'\n'
+                || b == b'\r'
+                || Some(b) == terminator
+                || Some(b) == escape
+        }) || (self.schema.comment.is_some()
+            && bytes.first() == self.schema.comment.as_ref());
 
         if !needs_quote {
             self.normalized.extend_from_slice(bytes);
             return;
         }
 
-        self.normalized.push(b'"');
+        self.normalized.push(quote);
         for &b in bytes {
-            if b == b'"' {
-                self.normalized.extend_from_slice(b"\"\"");
-            } else {
+            if b == quote {
+                self.normalized.push(quote_prefix);
+            } else if Some(b) == escape {
                 self.normalized.push(b);
             }
+            self.normalized.push(b);
         }
-        self.normalized.push(b'"');
+        self.normalized.push(quote);
     }
 
     fn push_err(&mut self, row: u32, col: u32, code: ErrorCode, kind: ColKind) {
+        self.push_err_detail(row, col, code, kind, 0);
+    }
+
+    fn push_err_detail(&mut self, row: u32, col: u32, code: ErrorCode, kind: ColKind, detail: u32) {
         if (self.errors.len() as u32) >= self.max_errors {
             return;
         }
-        self.errors.push(PackedError { row, col, code, kind });
+        let err = PackedError {
+            row,
+            col,
+            code,
+            kind,
+            detail,
+        };
+        self.errors.push(err);
+
+        if self.detail_value_limit.is_some() {
+            let expected = match kind {
+                ColKind::Schema => {
+                    details::expected_hint(&self.schema, &self.compiled, code, col, detail)
+                }
+                ColKind::Input => None,
+            };
+            self.error_details
+                .push(ErrorDetail::new(&err, self.locator.record(), expected));
+        }
+    }
+
+    /// Set the offending value on details pushed since `from`.
+    fn attach_value(&mut self, from: usize, raw: &[u8]) {
+        let Some(limit) = self.detail_value_limit else {
+            return;
+        };
+        for d in &mut self.error_details[from..] {
+            if d.value.is_none() {
+                d.set_value(raw, limit);
+            }
+        }
     }
 
     fn hit_error_limit(&self) -> bool {
@@ -563,6 +1592,24 @@ impl ValidatorEngine {
     }
 }
 
+fn build_reader(schema: &Schema) -> Reader {
+    build_reader_with(schema, schema.delimiter_byte(), schema.quote)
+}
+
+fn build_reader_with(schema: &Schema, delimiter: u8, quote: u8) -> Reader {
+    let mut rb = ReaderBuilder::new();
+    rb.delimiter(delimiter);
+    rb.quote(quote);
+    rb.escape(schema.escape);
+    rb.double_quote(schema.double_quote);
+    rb.comment(schema.comment);
+    rb.terminator(match schema.terminator {
+        RecordTerminator::Crlf => Terminator::CRLF,
+        RecordTerminator::Byte(t) => Terminator::Any(t),
+    });
+    rb.build()
+}
+
 fn trim_ascii(mut b: &[u8]) -> &[u8] {
     while let Some((&first, rest)) = b.split_first() {
         if first.is_ascii_whitespace() {
@@ -596,64 +1643,79 @@ fn is_valid_int(s: &str) -> bool {
     bs[i..].iter().all(|c| c.is_ascii_digit())
 }
 
-fn is_valid_decimal(s: &str, precision: u32) -> bool {
-    if s.contains(',') {
-        return false;
-    }
-    let d = match Decimal::from_str_exact(s) {
-        Ok(v) => v,
-        Err(_) => return false,
-    };
-    let rounded = d.round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero);
-    let mut fixed = rounded;
-    fixed.rescale(precision);
-    true
-}
-
-fn normalize_decimal(s: &str, precision: u32) -> Option<String> {
+fn parse_decimal(s: &str) -> Option<Decimal> {
     if s.contains(',') {
         return None;
     }
-    let d = Decimal::from_str_exact(s).ok()?;
-    let rounded = d.round_dp_with_strategy(precision, RoundingStrategy::MidpointAwayFromZero);
-    let mut fixed = rounded;
-    fixed.rescale(precision);
-    Some(fixed.to_string())
+    Decimal::from_str_exact(s).ok()
 }
 
-fn is_valid_date(s: &str, fmt: DateFormat) -> bool {
-    let parsed = match fmt {
-        DateFormat::YmdDash => parse_3_u32(s, b'-'),
-        DateFormat::DmySlash | DateFormat::MdySlash => parse_3_u32(s, b'/'),
-    };
-
-    let (p1, p2, p3) = match parsed {
+// `None` means the value is not representable as a Decimal (e.g. a huge int),
+// which is out of range for any configured bound.
+fn in_range(col: &schema::ColumnSpec, v: Option<Decimal>) -> bool {
+    if col.min.is_none() && col.max.is_none() {
+        return true;
+    }
+    let v = match v {
         Some(v) => v,
         None => return false,
     };
+    let above_min = match col.min {
+        Some(lo) if col.exclusive_min => v > lo,
+        Some(lo) => v >= lo,
+        None => true,
+    };
+    let below_max = match col.max {
+        Some(hi) if col.exclusive_max => v < hi,
+        Some(hi) => v <= hi,
+        None => true,
+    };
+    above_min && below_max
+}
 
-    let (y, m, d) = match fmt {
-        DateFormat::YmdDash => (p1, p2, p3),
-        DateFormat::DmySlash => (p3, p2, p1),
-        DateFormat::MdySlash => (p3, p1, p2),
+/// `cmp` orders the value against a bound (value.cmp(bound)).
+fn date_in_range(
+    col: &CompiledColumn,
+    cmp: impl Fn(temporal::DateBound) -> std::cmp::Ordering,
+) -> bool {
+    col.min_date.is_none_or(|lo| cmp(lo).is_ge()) && col.max_date.is_none_or(|hi| cmp(hi).is_le())
+}
+
+/// Bring `d` to the column's scale according to its scale mode, then check
+/// the total digit count. The error is the code to report.
+fn fit_decimal(d: Decimal, col: &schema::ColumnSpec) -> Result<Decimal, ErrorCode> {
+    let scale = col.decimal_scale();
+
+    // normalize(): "12.340" only has 2 significant decimals
+    let mut fixed = if d.normalize().scale() > scale {
+        match col.scale_mode {
+            ScaleMode::Round => {
+                d.round_dp_with_strategy(scale, RoundingStrategy::MidpointAwayFromZero)
+            }
+            ScaleMode::Truncate => d.round_dp_with_strategy(scale, RoundingStrategy::ToZero),
+            ScaleMode::Reject => return Err(ErrorCode::ScaleExceeded),
+        }
+    } else {
+        d
     };
+    fixed.rescale(scale);
 
-    if m < 1 || m > 12 || d < 1 || d > 31 {
-        return false;
+    if let Some(max_digits) = col.total_digits {
+        let digits = fixed.mantissa().unsigned_abs().to_string().len() as u32;
+        if digits > max_digits {
+            return Err(ErrorCode::TooManyDigits);
+        }
     }
+    Ok(fixed)
+}
 
-    time::Date::from_calendar_date(
-        y as i32,
-        match time::Month::try_from(m as u8) {
-            Ok(mm) => mm,
-            Err(_) => return false,
-        },
-        d as u8,
-    )
-    .is_ok()
+fn normalize_decimal(s: &str, col: &schema::ColumnSpec) -> Option<String> {
+    let d = parse_decimal(s)?;
+    fit_decimal(d, col).ok().map(|v| v.to_string())
 }
 
-fn normalize_date(s: &str, fmt: DateFormat) -> Option<String> {
+/// Parse a date in one of the built-in layouts.
+fn parse_date(s: &str, fmt: DateFormat) -> Option<time::Date> {
     let (p1, p2, p3) = match fmt {
         DateFormat::YmdDash => parse_3_u32(s, b'-')?,
         DateFormat::DmySlash | DateFormat::MdySlash => parse_3_u32(s, b'/')?,
@@ -665,23 +1727,20 @@ fn normalize_date(s: &str, fmt: DateFormat) -> Option<String> {
         DateFormat::MdySlash => (p3, p1, p2),
     };
 
-    if m < 1 || m > 12 || d < 1 || d > 31 {
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
         return None;
     }
 
-    let date = time::Date::from_calendar_date(
-        y as i32,
-        time::Month::try_from(m as u8).ok()?,
-        d as u8,
-    )
-    .ok()?;
+    time::Date::from_calendar_date(y as i32, time::Month::try_from(m as u8).ok()?, d as u8).ok()
+}
 
-    Some(format!(
-        "{:04}-{:02}-{:02}",
-        date.year(),
-        u8::from(date.month()),
-        date.day()
-    ))
+fn format_date(date: time::Date, fmt: DateFormat) -> String {
+    let (y, m, d) = (date.year(), u8::from(date.month()), date.day());
+    match fmt {
+        DateFormat::YmdDash => format!("{y:04}-{m:02}-{d:02}"),
+        DateFormat::DmySlash => format!("{d:02}/{m:02}/{y:04}"),
+        DateFormat::MdySlash => format!("{m:02}/{d:02}/{y:04}"),
+    }
 }
 
 fn parse_3_u32(s: &str, sep: u8) -> Option<(u32, u32, u32)> {
@@ -718,3 +1777,708 @@ fn parse_3_u32(s: &str, sep: u8) -> Option<(u32, u32, u32)> {
 
     Some((parts[0], parts[1], parts[2]))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn engine(schema: &str) -> ValidatorEngine {
+        ValidatorEngine::new(schema, 1000, true, None).unwrap()
+    }
+
+    /// (row, col, code) of every error so far.
+    fn errors(e: &ValidatorEngine) -> Vec<(u32, u32, &'static str)> {
+        e.errors
+            .iter()
+            .map(|err| (err.row, err.col, err.code.as_str()))
+            .collect()
+    }
+
+    /// Workbook with one sheet; each row is (sheet row number, inline string cells).
+    fn workbook(rows: &[(u32, Vec<&str>)]) -> Vec<u8> {
+        let mut sheet = String::new();
+        for (r, cells) in rows {
+            sheet.push_str(&format!("<row r=\"{r}\">"));
+            for (i, v) in cells.iter().enumerate() {
+                let col = (b'A' + i as u8) as char;
+                sheet.push_str(&format!(
+                    "<c r=\"{col}{r}\" t=\"inlineStr\"><is><t>{v}</t></is></c>"
+                ));
+            }
+            sheet.push_str("</row>");
+        }
+        workbook_xml(&sheet)
+    }
+
+    /// Workbook whose one sheet has the given `<sheetData>` content.
+    fn workbook_xml(rows: &str) -> Vec<u8> {
+        let sheet = format!("<worksheet><sheetData>{rows}</sheetData></worksheet>");
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let opts = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        let parts = [
+            (
+                "xl/workbook.xml",
+                r#"<workbook><sheets><sheet name="Data" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            ("xl/worksheets/sheet1.xml", sheet.as_str()),
+        ];
+        for (name, body) in parts {
+            zip.start_file(name, opts).unwrap();
+            zip.write_all(body.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn xlsx_normalized_output_is_not_capped() {
+        let mut e =
+            engine(r#"{"columns":[{"name":"id","type":"int"},{"name":"note","type":"string"}]}"#);
+        let note = "x".repeat(60);
+        let mut rows = vec![(1, vec!["id", "note"])];
+        let ids: Vec<String> = (1..=50_000).map(|i| i.to_string()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            rows.push((i as u32 + 2, vec![id.as_str(), note.as_str()]));
+        }
+
+        let prog = e.push_workbook(&workbook(&rows)).unwrap();
+        assert_eq!(prog.rows_processed, 50_000);
+
+        let out = e.take_normalized();
+        assert!(out.len() > e.normalized_buf_limit);
+        let lines: Vec<&[u8]> = out.split(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), 50_001);
+        assert_eq!(lines[49_999], format!("50000,{note}").as_bytes());
+    }
+
+    #[test]
+    fn csv_normalized_rows_are_never_cut() {
+        let mut e = engine(
+            r#"{"hasHeaders":false,"columns":[{"name":"a","type":"string"},{"name":"b","type":"string"}]}"#,
+        );
+        e.normalized_buf_limit = 8;
+        e.push_bytes(b"aaaa,bbbb\ncccc,dddd\n", true);
+        assert_eq!(e.take_normalized(), b"aaaa,bbbb\n");
+    }
+
+    #[test]
+    fn xlsx_errors_use_sheet_row_numbers() {
+        let mut e =
+            engine(r#"{"columns":[{"name":"id","type":"int"},{"name":"note","type":"string"}]}"#);
+        // Sheet rows 3 and 5 are empty and absent from the XML
+        let rows = [
+            (2, vec!["id", "note"]),
+            (4, vec!["1", "ok"]),
+            (6, vec!["x", "bad id"]),
+        ];
+        e.push_workbook(&workbook(&rows)).unwrap();
+        assert_eq!(errors(&e), vec![(6, 0, "InvalidType")]);
+    }
+
+    #[test]
+    fn unique_values_are_compared_normalized() {
+        let mut e = engine(
+            r#"{"primaryKey":["code","amount"],"columns":[
+                {"name":"id","type":"string","unique":true},
+                {"name":"amount","type":"decimal","unique":true},
+                {"name":"code","type":"string"}]}"#,
+        );
+        e.push_bytes(
+            b"id,amount,code\nA,1,x\n A ,1.0,x\nB,2.50,y\nC,2.5,y\n",
+            true,
+        );
+        assert_eq!(
+            errors(&e),
+            vec![
+                (3, 0, "DuplicateValue"),
+                (3, 1, "DuplicateValue"),
+                (3, 2, "DuplicateValue"),
+                (5, 1, "DuplicateValue"),
+                (5, 2, "DuplicateValue"),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalized_output_escapes_the_escape_byte() {
+        for double_quote in [true, false] {
+            let schema = format!(
+                r#"{{"hasHeaders":false,"escape":"\\","doubleQuote":{double_quote},
+                    "columns":[{{"name":"a","type":"string"}},{{"name":"b","type":"string"}}]}}"#
+            );
+            let mut e = engine(&schema);
+            e.push_bytes(b"\"x\\\\,y\",\"q\\\"t\"\n", true);
+            let out = e.take_normalized();
+
+            // Reading the output back gives the same values
+            let mut again = engine(&schema);
+            again.push_bytes(&out, true);
+            assert_eq!(again.take_normalized(), out);
+
+            let mut rdr = build_reader(&again.schema);
+            let (mut buf, mut ends) = ([0u8; 64], [0usize; 4]);
+            let (res, _, n, nends) = rdr.read_record(&out, &mut buf, &mut ends);
+            assert!(matches!(res, ReadRecordResult::Record));
+            assert_eq!(&buf[..n], b"x\\,yq\"t");
+            assert_eq!(&ends[..nends], &[4, 7]);
+        }
+    }
+
+    #[test]
+    fn strict_order_needs_every_schema_column() {
+        let schema = |order: &str| {
+            format!(
+                r#"{{"columnOrder":"{order}","columns":[
+                    {{"name":"a","type":"string","required":true}},
+                    {{"name":"b","type":"string"}},
+                    {{"name":"c","type":"string"}}]}}"#
+            )
+        };
+
+        // Optional "b" missing: "c" shifts into its place
+        let mut e = engine(&schema("strict"));
+        e.push_bytes(b"a,c\n1,2\n", true);
+        assert_eq!(
+            errors(&e),
+            vec![(0, 2, "ColumnOutOfOrder"), (0, 1, "MissingRequiredColumn")]
+        );
+
+        // Trailing optional column missing
+        let mut e = engine(&schema("prefix"));
+        e.push_bytes(b"a,b,x\n1,2,3\n", true);
+        assert_eq!(errors(&e), vec![(0, 2, "MissingRequiredColumn")]);
+
+        let mut e = engine(&schema("any"));
+        e.push_bytes(b"c,a\n1,2\n", true);
+        assert_eq!(errors(&e), vec![]);
+    }
+
+    #[test]
+    fn skip_rows_with_cr_and_crlf_line_ends() {
+        let schema = r#"{"skipRows":2,"columns":[
+            {"name":"id","type":"int"},{"name":"amt","type":"int"}]}"#;
+        for data in [
+            &b"Title\rmeta\rid,amt\r1,2\r3,x\r"[..],
+            &b"Title\r\nmeta\r\nid,amt\r\n1,2\r\n3,x\r\n"[..],
+        ] {
+            for size in [data.len(), 1] {
+                let mut e = engine(schema);
+                e.enable_error_details(10);
+                let mut rows = 0;
+                for chunk in data.chunks(size) {
+                    rows += e.push_bytes(chunk, false).rows_processed;
+                }
+                rows += e.push_bytes(b"", true).rows_processed;
+
+                assert_eq!(rows, 2);
+                assert_eq!(e.input_header_names, ["id", "amt"]);
+                assert_eq!(errors(&e), vec![(5, 1, "InvalidType")]);
+                assert_eq!(e.error_details[0].line, Some(5));
+                assert_eq!(e.header_line, Some(3));
+            }
+        }
+    }
+
+    #[test]
+    fn rows_are_physical_lines() {
+        let schema = r#"{"skipRows":1,"skipBlankLines":true,"primaryKey":["id"],"columns":[
+            {"name":"id","type":"int"},{"name":"note","type":"string"}]}"#;
+        // Line 1 skipped, header on line 3, a note spanning lines 5-6, blank
+        // lines 7 and 9, an unclosed quote on line 11
+        let data = b"Export\n\nid,note\n1,a\n2,\"two\nlines\"\n\nx,b\n , \n1,c\n4,\"open\n";
+        for size in [data.len(), 1] {
+            let mut e = engine(schema);
+            e.enable_error_details(10);
+            for chunk in data.chunks(size) {
+                e.push_bytes(chunk, false);
+            }
+            e.push_bytes(b"", true);
+
+            assert_eq!(e.header_line, Some(3));
+            assert_eq!(
+                errors(&e),
+                vec![
+                    (8, 0, "InvalidType"),
+                    (10, 0, "DuplicateValue"),
+                    (11, 1, "UnterminatedQuote"),
+                ]
+            );
+            assert_eq!(e.errors[1].detail, 4);
+            let lines: Vec<_> = e.error_details.iter().map(|d| d.line).collect();
+            assert_eq!(lines, [Some(8), Some(10), Some(11)]);
+        }
+    }
+
+    #[test]
+    fn xlsx_skip_rows_counts_sheet_rows() {
+        let mut e = engine(r#"{"skipRows":2,"columns":[{"name":"id","type":"int"}]}"#);
+        // Row 2 is empty, so the header is the first row present after row 1
+        let rows = [(1, vec!["Report"]), (3, vec!["id"]), (4, vec!["x"])];
+        e.push_workbook(&workbook(&rows)).unwrap();
+        assert_eq!(e.header_line, Some(3));
+        assert_eq!(errors(&e), vec![(4, 0, "InvalidType")]);
+    }
+
+    #[test]
+    fn xlsx_out_of_range_date_serials_are_invalid() {
+        let mut e = engine(
+            r#"{"hasHeaders":false,"columns":[{"name":"d","type":"date"},{"name":"t","type":"datetime"}]}"#,
+        );
+        let rows = r#"<row r="1"><c r="A1"><v>45292</v></c><c r="B1"><v>45292.5</v></c></row>
+            <row r="2"><c r="A2"><v>1E+15</v></c><c r="B2"><v>1E+15</v></c></row>
+            <row r="3"><c r="A3"><v>2958466</v></c><c r="B3"><v>-1</v></c></row>"#;
+        e.push_workbook(&workbook_xml(rows)).unwrap();
+        assert_eq!(
+            errors(&e),
+            vec![
+                (2, 0, "InvalidType"),
+                (2, 1, "InvalidType"),
+                (3, 0, "InvalidType"),
+                (3, 1, "InvalidType"),
+            ]
+        );
+        assert_eq!(
+            e.take_normalized(),
+            b"2024-01-01,2024-01-01T12:00:00Z\n,\n,\n"
+        );
+    }
+
+    #[test]
+    fn xlsx_cell_references_past_xfd_are_rejected() {
+        let mut e = engine(r#"{"hasHeaders":false,"columns":[{"name":"a","type":"string"}]}"#);
+        let rows = r#"<row r="1"><c r="XFD1" t="inlineStr"><is><t>last</t></is></c></row>"#;
+        e.push_workbook(&workbook_xml(rows)).unwrap();
+
+        let mut e = engine(r#"{"hasHeaders":false,"columns":[{"name":"a","type":"string"}]}"#);
+        let rows = r#"<row r="1"><c r="ZZZZZZZZ1" t="inlineStr"><is><t>x</t></is></c></row>"#;
+        let err = e.push_workbook(&workbook_xml(rows)).unwrap_err();
+        assert_eq!(err, "invalid cell reference \"ZZZZZZZZ1\"");
+        assert_eq!(e.take_normalized(), b"");
+    }
+
+    #[test]
+    fn comment_lines_end_at_the_terminator() {
+        let schema = |terminator: &str| {
+            format!(
+                r##"{{"hasHeaders":false,"comment":"#",{terminator}"columns":[
+                    {{"name":"a","type":"string"}},{{"name":"b","type":"string"}}]}}"##
+            )
+        };
+        let cases: [(&str, &[u8], &[u8]); 5] = [
+            (r#""terminator":";","#, b"#c;x,y;", b"x,y;"),
+            (r#""terminator":";","#, b"#c\nd;x,y;", b"x,y;"),
+            ("", b"#c\rx,y\rz,w\r", b"x,y\nz,w\n"),
+            ("", b"#c\r\nx,y\r\n#d\nz,w\n", b"x,y\nz,w\n"),
+            // Not a comment: the line starts inside a quoted field
+            ("", b"\"a\n#b\",c\n", b"\"a\n#b\",c\n"),
+        ];
+        for (terminator, input, want) in cases {
+            let mut whole = engine(&schema(terminator));
+            whole.push_bytes(input, true);
+            assert_eq!(whole.take_normalized(), want);
+
+            let mut split = engine(&schema(terminator));
+            for b in input {
+                split.push_bytes(std::slice::from_ref(b), false);
+            }
+            split.push_bytes(b"", true);
+            assert_eq!(split.take_normalized(), want);
+            assert!(errors(&split).is_empty());
+        }
+    }
+
+    #[test]
+    fn sniffs_delimiter_quote_and_header() {
+        let schema = r#"{"delimiter":"auto","columns":[
+            {"name":"name","type":"string"},{"name":"qty","type":"int"}]}"#;
+        let dialect = |e: &ValidatorEngine| {
+            let d: serde_json::Value = serde_json::from_str(&e.dialect_json()).unwrap();
+            (
+                d["delimiter"].clone(),
+                d["quote"].clone(),
+                d["hasHeaders"].clone(),
+            )
+        };
+
+        // Header names a column; waits for a whole line before deciding
+        let mut e = engine(schema);
+        e.push_bytes(b"name;qty\nfo", false);
+        assert_eq!(e.push_bytes(b"o;1\nbar;x\n", true).rows_processed, 2);
+        assert_eq!(dialect(&e), (";".into(), "\"".into(), true.into()));
+        assert_eq!(errors(&e), vec![(3, 1, "InvalidType")]);
+
+        // Single quotes wrap fields; no header because the first row has
+        // numbers where the header would have text
+        let mut e = engine(schema);
+        e.push_bytes(b"'a|b'|1\n'c'|2\n", true);
+        assert_eq!(dialect(&e), ("|".into(), "'".into(), false.into()));
+        assert_eq!(e.take_normalized(), b"'a|b'|1\nc|2\n");
+
+        // Text over numbers reads as a header even when it names no column
+        let mut e = engine(schema);
+        e.push_bytes(b"label\tcount\nfoo\t1\n", true);
+        assert_eq!(dialect(&e), ("\t".into(), "\"".into(), true.into()));
+        assert!(e.dialect_json().contains(r#""sniffed":true"#));
+    }
+
+    #[test]
+    fn transcodes_legacy_and_utf16_input() {
+        let schema = |encoding: &str| {
+            format!(
+                r#"{{"encoding":"{encoding}","hasHeaders":false,"columns":[
+                    {{"name":"a","type":"string"}},{{"name":"b","type":"string"}}]}}"#
+            )
+        };
+        let utf16 = |text: &str, le: bool| {
+            let mut bytes = if le {
+                vec![0xFF, 0xFE]
+            } else {
+                vec![0xFE, 0xFF]
+            };
+            for unit in text.encode_utf16() {
+                let pair = if le {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                };
+                bytes.extend_from_slice(&pair);
+            }
+            bytes
+        };
+        let text = "caf\u{e9},\u{20ac}5\n\u{1F600},x\n";
+        let cases = [
+            // 0x80 is the euro sign in windows-1252 but U+0080 in Latin-1
+            (
+                "windows-1252",
+                b"caf\xE9,\x805\n".to_vec(),
+                "caf\u{e9},\u{20ac}5\n",
+            ),
+            ("latin1", b"caf\xE9,\x805\n".to_vec(), "caf\u{e9},\u{80}5\n"),
+            ("auto", utf16(text, true), text),
+            ("auto", utf16(text, false), text),
+            ("utf-16le", utf16(text, true), text),
+        ];
+        for (encoding, input, want) in cases {
+            // Odd-sized chunks split code units and the BOM
+            let mut e = engine(&schema(encoding));
+            for chunk in input.chunks(3) {
+                e.push_bytes(chunk, false);
+            }
+            e.push_bytes(b"", true);
+            assert_eq!(String::from_utf8(e.take_normalized()).unwrap(), want);
+            assert!(errors(&e).is_empty());
+        }
+
+        let mut e = engine(&schema("auto"));
+        e.push_bytes(&utf16("a,b\n", false), true);
+        assert!(e.dialect_json().contains(r#""encoding":"utf-16be""#));
+    }
+
+    #[test]
+    fn header_matching_modes_and_aliases() {
+        let schema = |mode: &str| {
+            format!(
+                r#"{{"headerMatching":"{mode}","columns":[
+                    {{"name":"unit_price","type":"decimal","aliases":["Price"]}},
+                    {{"name":"qty","type":"int","aliases":["amount"]}}]}}"#
+            )
+        };
+        let mapping = |header: &[u8], mode: &str| {
+            let mut e = engine(&schema(mode));
+            e.push_bytes(header, true);
+            let m: serde_json::Value = serde_json::from_str(&e.header_mapping_json()).unwrap();
+            m.as_array()
+                .unwrap()
+                .iter()
+                .map(|h| {
+                    (
+                        h["column"].as_str().map(str::to_string),
+                        h["matchedBy"].clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let col = |c: &str, by: &str| (Some(c.to_string()), serde_json::Value::from(by));
+        let none = (None, serde_json::Value::Null);
+
+        let header = b"UNIT-PRICE.,Amount\n";
+        assert_eq!(mapping(header, "exact"), vec![none.clone(), none.clone()]);
+        assert_eq!(
+            mapping(header, "caseInsensitive"),
+            vec![none.clone(), col("qty", "alias")]
+        );
+        assert_eq!(
+            mapping(header, "normalized"),
+            vec![col("unit_price", "name"), col("qty", "alias")]
+        );
+        assert_eq!(
+            mapping(b"PRICE,QTY\n", "caseInsensitive"),
+            vec![col("unit_price", "alias"), col("qty", "name")]
+        );
+        assert_eq!(
+            mapping(b"Price,qty\n", "exact"),
+            vec![col("unit_price", "alias"), col("qty", "name")]
+        );
+    }
+
+    #[test]
+    fn colliding_names_and_aliases_are_rejected() {
+        let build = |mode: &str, a: &str, b: &str| {
+            ValidatorEngine::build(
+                &format!(
+                    r#"{{"headerMatching":"{mode}","columns":[
+                        {{"name":"unit_price","type":"decimal","aliases":[{a}]}},
+                        {{"name":"qty","type":"int","aliases":[{b}]}}]}}"#
+                ),
+                10,
+                false,
+                None,
+            )
+            .err()
+        };
+        assert_eq!(
+            build("exact", r#""amt""#, r#""amt""#).as_deref(),
+            Some(
+                r#"Invalid schema: "amt" (column "qty") matches the same headers as "amt" (column "unit_price")"#
+            )
+        );
+        assert!(build("exact", r#""qty""#, "").is_some());
+        assert!(build("exact", r#""Amt""#, r#""amt""#).is_none());
+        assert!(build("caseInsensitive", r#""Amt""#, r#""amt""#).is_some());
+        assert!(build("caseInsensitive", r#""Unit Price""#, "").is_none());
+        assert!(build("normalized", r#""Unit Price""#, "").is_none());
+        assert!(build("normalized", "", r#""Unit-Price""#).is_some());
+    }
+
+    #[test]
+    fn conditional_required_and_forbidden() {
+        let mut e = engine(
+            r#"{"columns":[
+                {"name":"kind","type":"string"},
+                {"name":"vat","type":"string","requiredIf":{"column":"kind","in":["b2b","gov"]}},
+                {"name":"email","type":"string","requiredUnless":{"column":"phone"}},
+                {"name":"phone","type":"string","forbiddenIf":{"column":"kind","equals":"anon"}}]}"#,
+        );
+        e.push_bytes(
+            b"kind,vat,email,phone\n\
+              b2b,,a@x,\n\
+              gov,V1,,\n\
+              b2c,,,555\n\
+              anon,,a@x,555\n\
+              anon,,a@x,\n",
+            true,
+        );
+        assert_eq!(
+            errors(&e),
+            vec![
+                (2, 1, "MissingRequired"),
+                (3, 2, "MissingRequired"),
+                (5, 3, "Forbidden"),
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup_sets_load_across_chunks() {
+        let mut e = engine(r#"{"columns":[{"name":"code","type":"string","lookup":"codes"}]}"#);
+        // BOM, a quoted value and the last value without a line end, split
+        // mid-value at every chunk boundary
+        let list = b"\xEF\xBB\xBFalpha,ignored\r\n\"be,ta\"\n  gamma  \ndelta";
+        for chunk in list.chunks(4) {
+            e.push_lookup_chunk("codes", chunk, false);
+        }
+        e.push_lookup_chunk("codes", b"", true);
+
+        e.push_bytes(
+            b"code\nalpha\n\"be,ta\"\ngamma\ndelta\nignored\nalph\n",
+            true,
+        );
+        assert_eq!(
+            errors(&e),
+            vec![(6, 0, "NotInLookup"), (7, 0, "NotInLookup")]
+        );
+    }
+
+    #[test]
+    fn allowed_values_map_to_canonical_spelling() {
+        let mut e = engine(
+            r#"{"columns":[
+                {"name":"status","type":"string","allowed":["Active"],
+                 "allowedMap":{"Closed":["done","finished"]},"allowedCaseInsensitive":true},
+                {"name":"tier","type":"string","allowed":["Gold"],"allowedMap":{"Silver":["ag"]}}]}"#,
+        );
+        e.push_bytes(
+            b"status,tier\nactive,Gold\n DONE ,ag\nClosed,gold\npending,AG\nFinished,Silver\n",
+            true,
+        );
+        assert_eq!(
+            errors(&e),
+            vec![
+                (4, 1, "NotAllowed"),
+                (5, 0, "NotAllowed"),
+                (5, 1, "NotAllowed")
+            ]
+        );
+        assert_eq!(
+            e.take_normalized(),
+            b"Active,Gold\nClosed,Silver\nClosed,gold\npending,AG\nClosed,Silver\n"
+        );
+    }
+
+    #[test]
+    fn length_units_and_char_classes() {
+        let schema = |unit: &str| {
+            format!(
+                r#"{{"hasHeaders":false,"columns":[
+                    {{"name":"a","type":"string","exactLen":2,"lengthUnit":"{unit}"}},
+                    {{"name":"b","type":"string","charClass":["ascii","alphanumeric"]}}]}}"#
+            )
+        };
+        // "e" + combining acute: 3 bytes, 2 chars, 1 grapheme; then a flag
+        // emoji: 8 bytes, 2 chars, 1 grapheme
+        let input = "e\u{301}\u{1F1EB}\u{1F1F7},ok\nab,a-b\nxy,caf\u{e9}\n";
+        let run = |unit: &str| {
+            let mut e = engine(&schema(unit));
+            e.push_bytes(input.as_bytes(), true);
+            e.errors
+                .iter()
+                .map(|err| (err.row, err.col, err.code.as_str(), err.detail))
+                .collect::<Vec<_>>()
+        };
+        let expected = |measured| {
+            vec![
+                (1, 0, "ExactLengthMismatch", measured),
+                (2, 1, "DisallowedCharacter", u32::from('-')),
+                (3, 1, "DisallowedCharacter", 0xE9),
+            ]
+        };
+        assert_eq!(run("bytes"), expected(11));
+        assert_eq!(run("chars"), expected(4));
+        assert_eq!(run("graphemes"), expected(0)[1..]);
+    }
+
+    #[test]
+    fn injection_flag_and_neutralize() {
+        let schema = |policy: &str| {
+            format!(
+                r#"{{"hasHeaders":false,"injectionPolicy":"{policy}","columns":[
+                    {{"name":"s","type":"string"}},{{"name":"n","type":"int"}}]}}"#
+            )
+        };
+        let input = b"=SUM(A1),-5\n\"\t@x\",1\na\x07b,2\nplain,3\n";
+
+        let mut flag = engine(&schema("flag"));
+        flag.push_bytes(input, true);
+        let found: Vec<_> = flag
+            .errors
+            .iter()
+            .map(|err| (err.row, err.col, err.code.as_str(), err.detail))
+            .collect();
+        // Only string columns are checked: -5 is a number
+        assert_eq!(
+            found,
+            vec![
+                (1, 0, "FormulaInjection", u32::from('=')),
+                (2, 0, "FormulaInjection", u32::from('\t')),
+                (3, 0, "ControlCharacter", 7),
+            ]
+        );
+        assert_eq!(
+            flag.take_normalized(),
+            b"=SUM(A1),-5\n@x,1\na\x07b,2\nplain,3\n"
+        );
+
+        let mut neutralize = engine(&schema("neutralize"));
+        neutralize.push_bytes(input, true);
+        assert!(errors(&neutralize).is_empty());
+        assert_eq!(
+            neutralize.take_normalized(),
+            b"'=SUM(A1),-5\n'@x,1\nab,2\nplain,3\n"
+        );
+    }
+
+    #[test]
+    fn ragged_row_policies() {
+        let run = |policy: &str| {
+            let mut e = engine(&format!(
+                r#"{{"raggedRows":"{policy}","columns":[
+                    {{"name":"a","type":"string"}},{{"name":"b","type":"int","required":true}}]}}"#
+            ));
+            e.push_bytes(b"a,b\nx\nx,1,extra\nx,2\n", true);
+            errors(&e)
+        };
+        assert_eq!(run("ignore"), vec![(2, 1, "MissingRequired")]);
+        assert_eq!(
+            run("pad"),
+            vec![(2, 1, "MissingRequired"), (3, 2, "RowTooLong")]
+        );
+        assert_eq!(run("truncate"), vec![(2, 1, "RowTooShort")]);
+        assert_eq!(
+            run("reject"),
+            vec![(2, 1, "RowTooShort"), (3, 2, "RowTooLong")]
+        );
+    }
+
+    #[test]
+    fn structural_errors() {
+        let mut e = engine(
+            r#"{"hasHeaders":false,"maxFieldBytes":6,"maxRecordBytes":12,"columns":[
+                {"name":"a","type":"string"},{"name":"b","type":"string"}]}"#,
+        );
+        let input: &[u8] = b"a\"b,c\n\"ab\"c,d\ntoolong,x\nabcdef,abcdef\nok,\"open\n";
+        for chunk in input.chunks(5) {
+            e.push_bytes(chunk, false);
+        }
+        e.push_bytes(b"", true);
+        let found: Vec<_> = e
+            .errors
+            .iter()
+            .map(|err| (err.row, err.col, err.code.as_str(), err.detail))
+            .collect();
+        // detail is the byte offset of the offending quote / byte
+        assert_eq!(
+            found,
+            vec![
+                (1, 0, "UnexpectedQuote", 1),
+                (2, 0, "UnexpectedQuote", 9),
+                (3, 0, "FieldTooLarge", 20),
+                (4, 1, "RecordTooLarge", 36),
+                (5, 1, "UnterminatedQuote", 41),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_and_empty_headers() {
+        let run = |mode: &str| {
+            let mut e = engine(&format!(
+                r#"{{"duplicateHeaders":"{mode}","columns":[
+                    {{"name":"a","type":"int"}},{{"name":"b","type":"string"}}]}}"#
+            ));
+            e.push_bytes(b"a,b,A,a, \n1,x,2,bad,y\n", true);
+            let found: Vec<_> = e
+                .errors
+                .iter()
+                .map(|err| (err.row, err.col, err.code.as_str(), err.detail))
+                .collect();
+            (found, e.take_normalized())
+        };
+        // Reported whatever the mode: detail is the first occurrence
+        let header = vec![(0, 3, "DuplicateHeader", 0), (0, 4, "EmptyHeader", 0)];
+
+        let (found, out) = run("first");
+        assert_eq!(found, header);
+        assert_eq!(out, b"1,x\n");
+
+        let (found, out) = run("last");
+        assert_eq!(
+            found,
+            [header.clone(), vec![(2, 0, "InvalidType", 0)]].concat()
+        );
+        assert_eq!(out, b"bad,x\n");
+
+        let (found, _) = run("all");
+        assert_eq!(found, [header, vec![(2, 0, "InvalidType", 0)]].concat());
+    }
+}
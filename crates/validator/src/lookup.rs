@@ -0,0 +1,129 @@
+// Named reference sets for `lookup` columns (foreign-key style checks).
+//
+// Sets are loaded by the host before data is pushed, either streamed as CSV
+// (one value per line, or the first column of each record) or as a packed
+// array. Values are compared after ASCII whitespace trimming.
+
+use csv_core::{ReadRecordResult, Reader};
+
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+pub struct LookupSets {
+    sets: HashMap<String, LookupSet>,
+}
+
+#[derive(Default)]
+struct LookupSet {
+    values: HashSet<Box<[u8]>>,
+    // Streaming state, present while a CSV load is in progress
+    loader: Option<Loader>,
+}
+
+struct Loader {
+    rdr: Reader,
+    out: Vec<u8>,
+    ends: Vec<usize>,
+    out_len: usize,
+    ends_len: usize,
+    first_record: bool,
+}
+
+impl LookupSets {
+    /// Feed a chunk of a CSV/newline list into set `name`; the first field of
+    /// every record is added. Values accumulate across loads.
+    pub fn push_chunk(&mut self, name: &str, chunk: &[u8], final_chunk: bool) {
+        let set = self.sets.entry(name.to_string()).or_default();
+        let mut loader = set.loader.take().unwrap_or_else(Loader::new);
+
+        // csv-core reads empty input as end of data
+        if !chunk.is_empty() {
+            loader.feed(chunk, &mut set.values);
+        }
+        if final_chunk {
+            loader.feed(&[], &mut set.values);
+        } else {
+            set.loader = Some(loader);
+        }
+    }
+
+    /// Add values given as one byte buffer plus end offsets (value `i` is
+    /// `data[ends[i-1]..ends[i]]`).
+    pub fn extend_packed(&mut self, name: &str, data: &[u8], ends: &[u32]) -> Result<(), String> {
+        let set = self.sets.entry(name.to_string()).or_default();
+        let mut start = 0usize;
+        for &end in ends {
+            let end = end as usize;
+            let value = data.get(start..end).ok_or_else(|| {
+                format!("lookup \"{name}\": end offset {end} is out of order or past the data")
+            })?;
+            insert(&mut set.values, value);
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// A set that was never loaded is empty: every value fails.
+    pub fn contains(&self, name: &str, value: &[u8]) -> bool {
+        self.sets
+            .get(name)
+            .is_some_and(|set| set.values.contains(value))
+    }
+}
+
+impl Loader {
+    fn new() -> Loader {
+        Loader {
+            rdr: Reader::new(),
+            out: vec![0u8; 4 * 1024],
+            ends: vec![0usize; 16],
+            out_len: 0,
+            ends_len: 0,
+            first_record: true,
+        }
+    }
+
+    // Empty input flushes the final record
+    fn feed(&mut self, mut input: &[u8], values: &mut HashSet<Box<[u8]>>) {
+        loop {
+            let (res, nin, nout, nends) = self.rdr.read_record(
+                input,
+                &mut self.out[self.out_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[nin..];
+            self.out_len += nout;
+            self.ends_len += nends;
+
+            match res {
+                ReadRecordResult::Record => {
+                    let nends = std::mem::take(&mut self.ends_len);
+                    self.out_len = 0;
+                    if nends == 0 {
+                        continue;
+                    }
+                    let mut value = &self.out[..self.ends[0]];
+                    if std::mem::take(&mut self.first_record) {
+                        value = value.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(value);
+                    }
+                    insert(values, value);
+                }
+                ReadRecordResult::InputEmpty => return,
+                ReadRecordResult::OutputFull => {
+                    self.out.resize(self.out.len() * 2, 0);
+                }
+                ReadRecordResult::OutputEndsFull => {
+                    self.ends.resize(self.ends.len() * 2, 0);
+                }
+                ReadRecordResult::End => return,
+            }
+        }
+    }
+}
+
+fn insert(values: &mut HashSet<Box<[u8]>>, value: &[u8]) {
+    let value = crate::trim_ascii(value);
+    if !value.is_empty() && !values.contains(value) {
+        values.insert(value.into());
+    }
+}
@@ -0,0 +1,829 @@
+// Row-level cross-field rules (`schema.rules`).
+//
+// A rule is a boolean expression over the typed values of a row, e.g.
+//   end_date >= start_date
+//   total == qty * unit_price
+//   !(country in ['DE', 'FR', 'IT']) || present(vat_id)
+//
+// Grammar (lowest to highest precedence):
+//   ||  &&  (== != < <= > >= in [..])  (+ -)  (* /)  (! -)  atoms
+// Atoms: numbers, 'strings' / "strings", true, false, null, column names
+// (bare identifiers or `back quoted`), parentheses and function calls:
+//   empty(x) present(x) len(x) lower(x) upper(x) abs(x) round(x, places)
+//
+// Empty cells are null. Null propagates through arithmetic and comparisons
+// (except == / !=), and && / || use three-valued logic. A row violates a
+// rule only when the expression is false; null means "not applicable".
+// Date - date is a number of days, date +/- number shifts by days.
+//
+// Expressions are parsed and type-checked once, at engine construction.
+// Nesting is capped (MAX_DEPTH) so that checking and evaluating a rule can
+// never exhaust the stack.
+
+use crate::schema::{ColumnType, Schema};
+
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::cmp::Ordering;
+use time::{Date, OffsetDateTime, Time};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Num(Decimal),
+    Str(String),
+    Date(Date),
+    DateTime(OffsetDateTime),
+    Time(Time),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    expr: Expr,
+    // Schema columns the expression reads, in order of first appearance
+    pub columns: Vec<usize>,
+    // Column reported with a violation (first of `columns` in the spec,
+    // else the first one the expression reads)
+    pub report_col: usize,
+}
+
+impl Rule {
+    /// `row` holds typed values indexed by schema column.
+    pub fn holds(&self, row: &[Value]) -> bool {
+        eval(&self.expr, row) != Value::Bool(false)
+    }
+}
+
+pub fn compile_rules(schema: &Schema) -> Result<Vec<Rule>, String> {
+    schema
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            compile_rule(&spec.expr, &spec.columns, schema)
+                .map_err(|e| format!("rule {i} (\"{}\"): {e}", spec.expr))
+        })
+        .collect()
+}
+
+fn compile_rule(src: &str, involved: &[String], schema: &Schema) -> Result<Rule, String> {
+    let mut parser = Parser {
+        toks: lex(src)?,
+        pos: 0,
+        schema,
+        columns: Vec::new(),
+        depth: 0,
+    };
+    let expr = parser.expr(0)?;
+    if let Some(t) = parser.toks.get(parser.pos) {
+        return Err(format!("unexpected {t:?}"));
+    }
+
+    match check(&expr, schema)? {
+        Ty::Bool | Ty::Null => {}
+        ty => return Err(format!("must be a true/false expression, not {ty:?}")),
+    }
+
+    let mut report_col = parser.columns.first().copied().unwrap_or(0);
+    if let Some(first) = involved.first() {
+        for name in involved {
+            column_index(schema, name)?;
+        }
+        report_col = column_index(schema, first)?;
+    }
+
+    Ok(Rule {
+        expr,
+        columns: parser.columns,
+        report_col,
+    })
+}
+
+fn column_index(schema: &Schema, name: &str) -> Result<usize, String> {
+    schema
+        .columns
+        .iter()
+        .position(|c| c.name == name)
+        .ok_or_else(|| format!("unknown column \"{name}\""))
+}
+
+// ---- Lexer ----
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Num(Decimal),
+    Str(String),
+    Ident(String),
+    True,
+    False,
+    Null,
+    In,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+    Not,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+}
+
+fn lex(src: &str) -> Result<Vec<Tok>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut toks = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let n = Decimal::from_str_exact(&text).map_err(|_| format!("invalid number {text}"))?;
+            toks.push(Tok::Num(n));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            toks.push(match word.as_str() {
+                "true" => Tok::True,
+                "false" => Tok::False,
+                "null" => Tok::Null,
+                "in" => Tok::In,
+                "and" => Tok::And,
+                "or" => Tok::Or,
+                "not" => Tok::Not,
+                _ => Tok::Ident(word),
+            });
+            continue;
+        }
+
+        if c == '\'' || c == '"' || c == '`' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("unterminated string or `name`".to_string()),
+                    Some(&q) if q == c => break,
+                    Some('\\') if c != '`' => {
+                        text.push(*chars.get(i + 1).ok_or("unterminated string")?);
+                        i += 2;
+                    }
+                    Some(&ch) => {
+                        text.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            toks.push(if c == '`' {
+                Tok::Ident(text)
+            } else {
+                Tok::Str(text)
+            });
+            continue;
+        }
+
+        let (tok, len) = match (c, next) {
+            ('=', Some('=')) => (Tok::Eq, 2),
+            ('!', Some('=')) => (Tok::Ne, 2),
+            ('<', Some('=')) => (Tok::Le, 2),
+            ('>', Some('=')) => (Tok::Ge, 2),
+            ('&', Some('&')) => (Tok::And, 2),
+            ('|', Some('|')) => (Tok::Or, 2),
+            ('<', _) => (Tok::Lt, 1),
+            ('>', _) => (Tok::Gt, 1),
+            ('!', _) => (Tok::Not, 1),
+            ('+', _) => (Tok::Plus, 1),
+            ('-', _) => (Tok::Minus, 1),
+            ('*', _) => (Tok::Star, 1),
+            ('/', _) => (Tok::Slash, 1),
+            ('(', _) => (Tok::LParen, 1),
+            (')', _) => (Tok::RParen, 1),
+            ('[', _) => (Tok::LBracket, 1),
+            (']', _) => (Tok::RBracket, 1),
+            (',', _) => (Tok::Comma, 1),
+            ('=', _) => return Err("use == to compare".to_string()),
+            _ => return Err(format!("unexpected character '{c}'")),
+        };
+        toks.push(tok);
+        i += len;
+    }
+
+    Ok(toks)
+}
+
+// ---- Parser (Pratt) ----
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Or => "||",
+            BinOp::And => "&&",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Empty,
+    Present,
+    Len,
+    Lower,
+    Upper,
+    Abs,
+    Round,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Lit(Value),
+    Col(usize),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+    In(Box<Expr>, Vec<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+const PREFIX_BP: u8 = 11;
+
+// Deepest expression tree a rule may build (parentheses, prefix operators,
+// function arguments and chained binary operators all count)
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    toks: Vec<Tok>,
+    pos: usize,
+    schema: &'a Schema,
+    columns: Vec<usize>,
+    // Tree depth above the expression being parsed
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Tok> {
+        let t = self.toks.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn expect(&mut self, want: Tok) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == want => Ok(()),
+            Some(t) => Err(format!("expected {want:?}, found {t:?}")),
+            None => Err(format!("expected {want:?} at end of expression")),
+        }
+    }
+
+    fn nest(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!(
+                "expression is nested more than {MAX_DEPTH} levels deep"
+            ));
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, min_bp: u8) -> Result<Expr, String> {
+        let depth = self.depth;
+        self.nest()?;
+
+        let mut lhs = match self.next() {
+            Some(Tok::Num(n)) => Expr::Lit(Value::Num(n)),
+            Some(Tok::Str(s)) => Expr::Lit(Value::Str(s)),
+            Some(Tok::True) => Expr::Lit(Value::Bool(true)),
+            Some(Tok::False) => Expr::Lit(Value::Bool(false)),
+            Some(Tok::Null) => Expr::Lit(Value::Null),
+            Some(Tok::Ident(name)) if self.toks.get(self.pos) == Some(&Tok::LParen) => {
+                self.call(&name)?
+            }
+            Some(Tok::Ident(name)) => {
+                let idx = column_index(self.schema, &name)?;
+                if !self.columns.contains(&idx) {
+                    self.columns.push(idx);
+                }
+                Expr::Col(idx)
+            }
+            Some(Tok::LParen) => {
+                let e = self.expr(0)?;
+                self.expect(Tok::RParen)?;
+                e
+            }
+            Some(Tok::Not) => Expr::Not(Box::new(self.expr(PREFIX_BP)?)),
+            Some(Tok::Minus) => Expr::Neg(Box::new(self.expr(PREFIX_BP)?)),
+            Some(t) => return Err(format!("unexpected {t:?}")),
+            None => return Err("unexpected end of expression".to_string()),
+        };
+
+        loop {
+            let (op, l_bp, r_bp) = match self.toks.get(self.pos) {
+                Some(Tok::Or) => (BinOp::Or, 1, 2),
+                Some(Tok::And) => (BinOp::And, 3, 4),
+                Some(Tok::Eq) => (BinOp::Eq, 5, 6),
+                Some(Tok::Ne) => (BinOp::Ne, 5, 6),
+                Some(Tok::Lt) => (BinOp::Lt, 5, 6),
+                Some(Tok::Le) => (BinOp::Le, 5, 6),
+                Some(Tok::Gt) => (BinOp::Gt, 5, 6),
+                Some(Tok::Ge) => (BinOp::Ge, 5, 6),
+                Some(Tok::In) if min_bp <= 5 => {
+                    self.nest()?;
+                    self.pos += 1;
+                    lhs = Expr::In(Box::new(lhs), self.list()?);
+                    continue;
+                }
+                Some(Tok::Plus) => (BinOp::Add, 7, 8),
+                Some(Tok::Minus) => (BinOp::Sub, 7, 8),
+                Some(Tok::Star) => (BinOp::Mul, 9, 10),
+                Some(Tok::Slash) => (BinOp::Div, 9, 10),
+                _ => break,
+            };
+            if l_bp < min_bp {
+                break;
+            }
+            // Each operator puts the tree built so far one level deeper
+            self.nest()?;
+            self.pos += 1;
+            let rhs = self.expr(r_bp)?;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        self.depth = depth;
+        Ok(lhs)
+    }
+
+    // [a, b, c]
+    fn list(&mut self) -> Result<Vec<Expr>, String> {
+        self.expect(Tok::LBracket)?;
+        let mut items = Vec::new();
+        if self.toks.get(self.pos) == Some(&Tok::RBracket) {
+            self.pos += 1;
+            return Ok(items);
+        }
+        loop {
+            items.push(self.expr(0)?);
+            match self.next() {
+                Some(Tok::Comma) => {}
+                Some(Tok::RBracket) => return Ok(items),
+                _ => return Err("expected , or ] in list".to_string()),
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let (func, arity) = match name {
+            "empty" => (Func::Empty, 1),
+            "present" => (Func::Present, 1),
+            "len" => (Func::Len, 1),
+            "lower" => (Func::Lower, 1),
+            "upper" => (Func::Upper, 1),
+            "abs" => (Func::Abs, 1),
+            "round" => (Func::Round, 2),
+            _ => return Err(format!("unknown function {name}()")),
+        };
+
+        self.expect(Tok::LParen)?;
+        let mut args = Vec::new();
+        if self.toks.get(self.pos) != Some(&Tok::RParen) {
+            loop {
+                args.push(self.expr(0)?);
+                if self.toks.get(self.pos) == Some(&Tok::Comma) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect(Tok::RParen)?;
+
+        if args.len() != arity {
+            return Err(format!("{name}() takes {arity} argument(s)"));
+        }
+        Ok(Expr::Call(func, args))
+    }
+}
+
+// ---- Type check ----
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Ty {
+    Null,
+    Bool,
+    Number,
+    Text,
+    Date,
+    DateTime,
+    Time,
+}
+
+fn column_ty(t: ColumnType) -> Ty {
+    match t {
+        ColumnType::String => Ty::Text,
+        ColumnType::Int | ColumnType::Decimal => Ty::Number,
+        ColumnType::Date => Ty::Date,
+        ColumnType::Datetime => Ty::DateTime,
+        ColumnType::Time => Ty::Time,
+        ColumnType::Bool => Ty::Bool,
+    }
+}
+
+// Null (the literal) fits any type
+fn unify(a: Ty, b: Ty) -> Option<Ty> {
+    match (a, b) {
+        (Ty::Null, t) | (t, Ty::Null) => Some(t),
+        (a, b) if a == b => Some(a),
+        _ => None,
+    }
+}
+
+fn check(e: &Expr, schema: &Schema) -> Result<Ty, String> {
+    let want = |e: &Expr, ty: Ty, what: &str| -> Result<(), String> {
+        let got = check(e, schema)?;
+        if unify(got, ty).is_none() {
+            return Err(format!("{what} needs {ty:?}, got {got:?}"));
+        }
+        Ok(())
+    };
+
+    Ok(match e {
+        Expr::Lit(v) => match v {
+            Value::Null => Ty::Null,
+            Value::Bool(_) => Ty::Bool,
+            Value::Num(_) => Ty::Number,
+            Value::Str(_) => Ty::Text,
+            Value::Date(_) => Ty::Date,
+            Value::DateTime(_) => Ty::DateTime,
+            Value::Time(_) => Ty::Time,
+        },
+        Expr::Col(i) => column_ty(schema.columns[*i].col_type),
+        Expr::Not(x) => {
+            want(x, Ty::Bool, "!")?;
+            Ty::Bool
+        }
+        Expr::Neg(x) => {
+            want(x, Ty::Number, "unary -")?;
+            Ty::Number
+        }
+        Expr::In(x, items) => {
+            let ty = check(x, schema)?;
+            for item in items {
+                want(item, ty, "in")?;
+            }
+            Ty::Bool
+        }
+        Expr::Call(f, args) => {
+            let arg = check(&args[0], schema)?;
+            match f {
+                Func::Empty | Func::Present => Ty::Bool,
+                Func::Len => {
+                    want(&args[0], Ty::Text, "len()")?;
+                    Ty::Number
+                }
+                Func::Lower | Func::Upper => {
+                    want(&args[0], Ty::Text, "lower()/upper()")?;
+                    Ty::Text
+                }
+                Func::Abs | Func::Round => {
+                    want(&args[0], Ty::Number, "abs()/round()")?;
+                    if *f == Func::Round {
+                        want(&args[1], Ty::Number, "round() places")?;
+                    }
+                    unify(arg, Ty::Number).unwrap_or(Ty::Number)
+                }
+            }
+        }
+        Expr::Bin(op, a, b) => {
+            let (ta, tb) = (check(a, schema)?, check(b, schema)?);
+            let mismatch = || format!("cannot apply {} to {ta:?} and {tb:?}", op.symbol());
+            match op {
+                BinOp::Or | BinOp::And => {
+                    unify(ta, Ty::Bool).ok_or_else(mismatch)?;
+                    unify(tb, Ty::Bool).ok_or_else(mismatch)?;
+                    Ty::Bool
+                }
+                BinOp::Eq | BinOp::Ne => {
+                    unify(ta, tb).ok_or_else(mismatch)?;
+                    Ty::Bool
+                }
+                BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => match unify(ta, tb) {
+                    Some(Ty::Bool) | None => return Err(mismatch()),
+                    Some(_) => Ty::Bool,
+                },
+                BinOp::Add | BinOp::Sub => match (ta, tb) {
+                    (Ty::Date, Ty::Number | Ty::Null) => Ty::Date,
+                    (Ty::Date, Ty::Date) if *op == BinOp::Sub => Ty::Number,
+                    (Ty::Text, Ty::Text | Ty::Null) if *op == BinOp::Add => Ty::Text,
+                    _ => match unify(ta, tb) {
+                        Some(Ty::Number | Ty::Null) => Ty::Number,
+                        _ => return Err(mismatch()),
+                    },
+                },
+                BinOp::Mul | BinOp::Div => match unify(ta, tb) {
+                    Some(Ty::Number | Ty::Null) => Ty::Number,
+                    _ => return Err(mismatch()),
+                },
+            }
+        }
+    })
+}
+
+// ---- Evaluation ----
+
+fn eval(e: &Expr, row: &[Value]) -> Value {
+    match e {
+        Expr::Lit(v) => v.clone(),
+        Expr::Col(i) => row[*i].clone(),
+        Expr::Not(x) => match eval(x, row) {
+            Value::Bool(b) => Value::Bool(!b),
+            _ => Value::Null,
+        },
+        Expr::Neg(x) => match eval(x, row) {
+            Value::Num(n) => Value::Num(-n),
+            _ => Value::Null,
+        },
+        Expr::In(x, items) => match eval(x, row) {
+            Value::Null => Value::Null,
+            v => Value::Bool(items.iter().any(|item| eval(item, row) == v)),
+        },
+        Expr::Call(f, args) => call(*f, args, row),
+        Expr::Bin(BinOp::And, a, b) => match (eval(a, row), eval(b, row)) {
+            (Value::Bool(false), _) | (_, Value::Bool(false)) => Value::Bool(false),
+            (Value::Bool(true), Value::Bool(true)) => Value::Bool(true),
+            _ => Value::Null,
+        },
+        Expr::Bin(BinOp::Or, a, b) => match (eval(a, row), eval(b, row)) {
+            (Value::Bool(true), _) | (_, Value::Bool(true)) => Value::Bool(true),
+            (Value::Bool(false), Value::Bool(false)) => Value::Bool(false),
+            _ => Value::Null,
+        },
+        Expr::Bin(op, a, b) => binary(*op, eval(a, row), eval(b, row)),
+    }
+}
+
+fn binary(op: BinOp, a: Value, b: Value) -> Value {
+    match op {
+        BinOp::Eq => return Value::Bool(a == b),
+        BinOp::Ne => return Value::Bool(a != b),
+        _ => {}
+    }
+    if a == Value::Null || b == Value::Null {
+        return Value::Null;
+    }
+
+    let ord = |want: fn(Ordering) -> bool| match compare(&a, &b) {
+        Some(o) => Value::Bool(want(o)),
+        None => Value::Null,
+    };
+    let num = |v: Option<Decimal>| v.map_or(Value::Null, Value::Num);
+
+    match (op, &a, &b) {
+        (BinOp::Lt, _, _) => ord(Ordering::is_lt),
+        (BinOp::Le, _, _) => ord(Ordering::is_le),
+        (BinOp::Gt, _, _) => ord(Ordering::is_gt),
+        (BinOp::Ge, _, _) => ord(Ordering::is_ge),
+        (BinOp::Add, Value::Num(x), Value::Num(y)) => num(x.checked_add(*y)),
+        (BinOp::Sub, Value::Num(x), Value::Num(y)) => num(x.checked_sub(*y)),
+        (BinOp::Mul, Value::Num(x), Value::Num(y)) => num(x.checked_mul(*y)),
+        (BinOp::Div, Value::Num(x), Value::Num(y)) => num(x.checked_div(*y)),
+        (BinOp::Add, Value::Str(x), Value::Str(y)) => Value::Str(format!("{x}{y}")),
+        (BinOp::Sub, Value::Date(x), Value::Date(y)) => {
+            Value::Num(Decimal::from((*x - *y).whole_days()))
+        }
+        (BinOp::Add | BinOp::Sub, Value::Date(d), Value::Num(n)) => {
+            // Anything past the whole calendar misses; Duration::days would
+            // overflow long before i64 does
+            let span = i64::from(Date::MAX.to_julian_day() - Date::MIN.to_julian_day());
+            let days = i64::try_from(n.trunc())
+                .ok()
+                .filter(|d| d.abs() <= span)
+                .map(time::Duration::days);
+            let shifted = match (op, days) {
+                (BinOp::Add, Some(days)) => d.checked_add(days),
+                (_, Some(days)) => d.checked_sub(days),
+                _ => None,
+            };
+            shifted.map_or(Value::Null, Value::Date)
+        }
+        _ => Value::Null,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    Some(match (a, b) {
+        (Value::Num(x), Value::Num(y)) => x.cmp(y),
+        (Value::Str(x), Value::Str(y)) => x.cmp(y),
+        (Value::Date(x), Value::Date(y)) => x.cmp(y),
+        (Value::DateTime(x), Value::DateTime(y)) => x.cmp(y),
+        (Value::Time(x), Value::Time(y)) => x.cmp(y),
+        _ => return None,
+    })
+}
+
+fn call(f: Func, args: &[Expr], row: &[Value]) -> Value {
+    let v = eval(&args[0], row);
+    let is_empty = matches!(&v, Value::Null) || matches!(&v, Value::Str(s) if s.is_empty());
+    match (f, v) {
+        (Func::Empty, _) => Value::Bool(is_empty),
+        (Func::Present, _) => Value::Bool(!is_empty),
+        (Func::Len, Value::Str(s)) => Value::Num(Decimal::from(s.chars().count())),
+        (Func::Lower, Value::Str(s)) => Value::Str(s.to_lowercase()),
+        (Func::Upper, Value::Str(s)) => Value::Str(s.to_uppercase()),
+        (Func::Abs, Value::Num(n)) => Value::Num(n.abs()),
+        (Func::Round, Value::Num(n)) => match eval(&args[1], row) {
+            Value::Num(places) => match u32::try_from(places.trunc()) {
+                Ok(dp) if dp <= 28 => {
+                    Value::Num(n.round_dp_with_strategy(dp, RoundingStrategy::MidpointAwayFromZero))
+                }
+                _ => Value::Null,
+            },
+            _ => Value::Null,
+        },
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        serde_json::from_str(
+            r#"{"columns":[
+                {"name":"a","type":"int"},
+                {"name":"b","type":"decimal"},
+                {"name":"s","type":"string"},
+                {"name":"start","type":"date"},
+                {"name":"end","type":"date"},
+                {"name":"ok","type":"bool"}]}"#,
+        )
+        .unwrap()
+    }
+
+    fn num(n: i64) -> Value {
+        Value::Num(Decimal::from(n))
+    }
+
+    fn date(y: i32, d: u8) -> Value {
+        Value::Date(Date::from_calendar_date(y, time::Month::January, d).unwrap())
+    }
+
+    // a, b, s, start, end, ok
+    fn row() -> Vec<Value> {
+        vec![
+            num(2),
+            Value::Num(Decimal::new(150, 2)),
+            Value::Str("Beta".to_string()),
+            date(2024, 10),
+            date(2024, 31),
+            Value::Bool(true),
+        ]
+    }
+
+    fn holds(src: &str, row: &[Value]) -> bool {
+        compile_rule(src, &[], &schema()).unwrap().holds(row)
+    }
+
+    fn error(src: &str) -> String {
+        compile_rule(src, &[], &schema()).unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        let r = row();
+        assert!(holds("1 + 2 * 3 == 7", &r));
+        assert!(holds("(1 + 2) * 3 == 9", &r));
+        assert!(holds("10 - 4 - 3 == 3", &r));
+        assert!(holds("-a * 3 == -6", &r));
+        assert!(holds("true || false && false", &r));
+        assert!(!holds("!false && false", &r));
+        assert!(holds("a + 1 in [3, 4]", &r));
+        assert!(holds("not (a > 5) and ok", &r));
+    }
+
+    #[test]
+    fn comparisons_by_type() {
+        let r = row();
+        assert!(holds("b > 1 && b < a", &r));
+        assert!(holds("a == 2.0", &r));
+        assert!(holds("s > 'Alpha' && lower(s) == 'beta'", &r));
+        assert!(holds("end >= start && end - start == 21", &r));
+        assert!(holds("start + 21 == end", &r));
+        assert!(holds("round(b / 4, 2) == 0.38 && len(s) == 4", &r));
+
+        assert!(error("a < s").contains("cannot apply <"));
+        assert!(error("ok < ok").contains("cannot apply <"));
+        assert!(error("start == 1").contains("cannot apply =="));
+        assert!(error("s in [1, 2]").contains("in needs Text"));
+    }
+
+    #[test]
+    fn nulls_are_not_applicable() {
+        let mut r = row();
+        r[0] = Value::Null;
+
+        // Comparisons and arithmetic with null are "not applicable"
+        assert!(holds("a > 100", &r));
+        assert!(holds("a + 1 < 0", &r));
+        assert!(holds("a in [7]", &r));
+        // == / != see null as a value
+        assert!(holds("a == null", &r));
+        assert!(!holds("a != null", &r));
+        assert!(!holds("present(a)", &r));
+        assert!(holds("empty(a)", &r));
+        // Three-valued && / ||
+        assert!(holds("a > 1 || b > 1", &r));
+        assert!(!holds("a > 1 && b > 9", &r));
+        assert!(holds("a > 1 && b > 1", &r));
+    }
+
+    #[test]
+    fn date_shifts_out_of_range_are_null() {
+        let r = row();
+        assert!(holds("start + 100000000000000000 == null", &r));
+        assert!(holds("start - 100000000000000000 == null", &r));
+        assert!(holds("start + 3000000 == null", &r));
+        assert!(holds("start - 8000 > start - 9000", &r));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(error("a = 1"), "use == to compare");
+        assert_eq!(error("a >"), "unexpected end of expression");
+        assert!(error("(a > 1").contains("expected RParen"));
+        assert!(error("a > 1 b").starts_with("unexpected"));
+        assert_eq!(error("foo(a) > 1"), "unknown function foo()");
+        assert_eq!(error("round(b) > 1"), "round() takes 2 argument(s)");
+        assert_eq!(error("x > 1"), "unknown column \"x\"");
+        assert_eq!(error("s == 'abc"), "unterminated string or `name`");
+        assert!(error("a + 1").starts_with("must be a true/false expression"));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let deep = [
+            format!("{}a{} > 0", "(".repeat(100_000), ")".repeat(100_000)),
+            format!("{}ok", "!".repeat(100_000)),
+            format!("{}a > 0", "-".repeat(100_000)),
+            format!("a{} > 0", " + 1".repeat(100_000)),
+            format!("ok{}", " && ok".repeat(100_000)),
+            format!("{}1{}", "abs(".repeat(100_000), ")".repeat(100_000)),
+        ];
+        for src in &deep {
+            assert!(error(src).contains("nested more than"));
+        }
+
+        let ok = format!("{}a{} + 1 > 0", "(".repeat(100), ")".repeat(100));
+        assert!(holds(&ok, &row()));
+    }
+}
@@ -1,47 +1,402 @@
-use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
 use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
+use unicode_segmentation::UnicodeSegmentation;
 
-fn default_delimiter() -> u8 {
-    b','
+fn default_delimiter() -> Delimiter {
+    Delimiter::Byte(b',')
 }
 
-// Accept delimiter as "," or 44
-fn deserialize_delimiter<'de, D>(deserializer: D) -> Result<u8, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let v = Value::deserialize(deserializer)?;
+fn default_quote() -> u8 {
+    b'"'
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn byte_from_value<E: de::Error>(v: Value, what: &str) -> Result<u8, E> {
     match v {
         Value::Number(n) => n
             .as_u64()
             .and_then(|x| u8::try_from(x).ok())
-            .ok_or_else(|| de::Error::custom("delimiter must be a u8 (0..=255)")),
+            .ok_or_else(|| E::custom(format!("{what} must be a u8 (0..=255)"))),
         Value::String(s) => {
             let b = s.as_bytes();
             if b.len() == 1 {
                 Ok(b[0])
             } else {
-                Err(de::Error::custom("delimiter must be a single character string (e.g. \",\" or \";\")"))
+                Err(E::custom(format!(
+                    "{what} must be a single character string (e.g. \",\" or \";\")"
+                )))
             }
         }
-        _ => Err(de::Error::custom("delimiter must be a number or a single character string")),
+        _ => Err(E::custom(format!(
+            "{what} must be a number or a single character string"
+        ))),
+    }
+}
+
+// Accept delimiter as "," or 44, or "auto" to sniff it from the data
+fn deserialize_delimiter<'de, D>(deserializer: D) -> Result<Delimiter, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(s) if s == "auto" => Ok(Delimiter::Auto),
+        v => byte_from_value(v, "delimiter").map(Delimiter::Byte),
+    }
+}
+
+// Accept quote as "'" or 39
+fn deserialize_quote<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    byte_from_value(Value::deserialize(deserializer)?, "quote")
+}
+
+// Optional single-byte settings (escape, comment); null means "off"
+fn deserialize_opt_byte<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        v => byte_from_value(v, "escape/comment").map(Some),
+    }
+}
+
+// Accept terminator as "crlf" (any of \r, \n, \r\n) or a single character
+fn deserialize_terminator<'de, D>(deserializer: D) -> Result<RecordTerminator, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(s) if s.eq_ignore_ascii_case("crlf") => Ok(RecordTerminator::Crlf),
+        v => byte_from_value(v, "terminator").map(RecordTerminator::Byte),
+    }
+}
+
+// Accept numeric bounds as 10, 0.5 or "12.345" (parsed exactly, never via f64)
+fn deserialize_bound<'de, D>(deserializer: D) -> Result<Option<Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let v = Value::deserialize(deserializer)?;
+    let text = match v {
+        Value::Null => return Ok(None),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s,
+        _ => {
+            return Err(de::Error::custom(
+                "bound must be a number or a numeric string",
+            ))
+        }
+    };
+    Decimal::from_str_exact(text.trim())
+        .or_else(|_| Decimal::from_scientific(text.trim()))
+        .map(Some)
+        .map_err(|_| de::Error::custom(format!("invalid numeric bound \"{text}\"")))
+}
+
+// Accept sheet as "Sheet1" (name) or 0 (zero-based index)
+fn deserialize_sheet<'de, D>(deserializer: D) -> Result<Option<SheetSelector>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let v = Value::deserialize(deserializer)?;
+    match v {
+        Value::Null => Ok(None),
+        Value::Number(n) => n
+            .as_u64()
+            .and_then(|x| usize::try_from(x).ok())
+            .map(|i| Some(SheetSelector::Index(i)))
+            .ok_or_else(|| de::Error::custom("sheet index must be a non-negative integer")),
+        Value::String(s) => Ok(Some(SheetSelector::Name(s))),
+        _ => Err(de::Error::custom(
+            "sheet must be a sheet name or a zero-based index",
+        )),
+    }
+}
+
+// Accept dateFormat as one format or a list tried in order
+fn deserialize_date_formats<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let v = Value::deserialize(deserializer)?;
+    match v {
+        Value::Null => Ok(Vec::new()),
+        Value::String(s) => Ok(vec![s]),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s),
+                _ => Err(de::Error::custom("dateFormat entries must be strings")),
+            })
+            .collect(),
+        _ => Err(de::Error::custom(
+            "dateFormat must be a format string or a list of format strings",
+        )),
+    }
+}
+
+fn deserialize_char_classes<'de, D>(deserializer: D) -> Result<Vec<CharClass>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let v = Value::deserialize(deserializer)?;
+    match v {
+        Value::Null => Ok(Vec::new()),
+        Value::String(_) => Ok(vec![CharClass::deserialize(v).map_err(de::Error::custom)?]),
+        Value::Array(_) => Vec::<CharClass>::deserialize(v).map_err(de::Error::custom),
+        _ => Err(de::Error::custom(
+            "charClass must be a class name or a list of class names",
+        )),
     }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Schema {
-    pub has_headers: bool,
+    // None: sniffed when delimiter is "auto", otherwise true
+    #[serde(default)]
+    pub has_headers: Option<bool>,
+
+    #[serde(
+        default = "default_delimiter",
+        deserialize_with = "deserialize_delimiter"
+    )]
+    pub delimiter: Delimiter, // stored as byte internally once resolved
+
+    #[serde(default = "default_quote", deserialize_with = "deserialize_quote")]
+    pub quote: u8,
+
+    // Escape inside quoted fields (e.g. "\\"): the byte after it is taken
+    // literally. Applies whether or not double_quote is on
+    #[serde(default, deserialize_with = "deserialize_opt_byte")]
+    pub escape: Option<u8>,
 
-    #[serde(default = "default_delimiter", deserialize_with = "deserialize_delimiter")]
-    pub delimiter: u8, // stored as byte internally
+    // "" inside a quoted field is a literal quote
+    #[serde(default = "default_true")]
+    pub double_quote: bool,
+
+    // Lines starting with this byte are skipped
+    #[serde(default, deserialize_with = "deserialize_opt_byte")]
+    pub comment: Option<u8>,
+
+    #[serde(default, deserialize_with = "deserialize_terminator")]
+    pub terminator: RecordTerminator,
 
     #[serde(default)]
     pub columns: Vec<ColumnSpec>,
 
     #[serde(default)]
     pub fail_on_extra_columns: bool,
+
+    // Size limits on raw fields / records (bytes as parsed, quotes included)
+    #[serde(default)]
+    pub max_field_bytes: Option<usize>,
+
+    #[serde(default)]
+    pub max_record_bytes: Option<usize>,
+
+    // Records whose field count differs from the header (or schema) width
+    #[serde(default)]
+    pub ragged_rows: RaggedRows,
+
+    // How input headers are compared with column names/aliases
+    #[serde(default)]
+    pub header_matching: HeaderMatching,
+
+    // Physical lines dropped before parsing (XLSX: sheet rows 1..=skipRows,
+    // empty or not)
+    #[serde(default)]
+    pub skip_rows: usize,
+
+    // 1-based record (after skipRows) holding the header; earlier records
+    // are ignored. Implies hasHeaders.
+    #[serde(default)]
+    pub header_row: Option<usize>,
+
+    // Ignore records whose fields are all blank (",,," or whitespace);
+    // they still use up a row number
+    #[serde(default)]
+    pub skip_blank_lines: bool,
+
+    // Positional requirements on the header (checked once, at row 0)
+    #[serde(default)]
+    pub column_order: ColumnOrder,
+
+    // Which of several same-named input columns feeds the schema column
+    #[serde(default)]
+    pub duplicate_headers: DuplicateHeaders,
+
+    // Input byte encoding; chunks are transcoded to UTF-8 before parsing
+    #[serde(default)]
+    pub encoding: InputEncoding,
+
+    // Composite key (column names) that must be unique across rows; parts
+    // are compared like `unique` values
+    #[serde(default)]
+    pub primary_key: Vec<String>,
+
+    #[serde(default)]
+    pub unique_mode: UniqueMode,
+
+    // XLSX only: which worksheet to read (defaults to the first one)
+    #[serde(default, deserialize_with = "deserialize_sheet")]
+    pub sheet: Option<SheetSelector>,
+
+    // Cross-field checks evaluated per row (see rules.rs for the syntax)
+    #[serde(default)]
+    pub rules: Vec<RuleSpec>,
+
+    // Formula-like cells and control characters in string columns
+    #[serde(default)]
+    pub injection_policy: InjectionPolicy,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleSpec {
+    pub expr: String,
+
+    // Shown for violations (defaults to the expression)
+    #[serde(default)]
+    pub message: Option<String>,
+
+    // Columns the rule is about; the first one is reported
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+impl RuleSpec {
+    pub fn message(&self) -> &str {
+        self.message.as_deref().unwrap_or(&self.expr)
+    }
+}
+
+impl Schema {
+    pub fn has_headers(&self) -> bool {
+        self.has_headers.unwrap_or(true)
+    }
+
+    /// Field delimiter byte; ',' while an "auto" delimiter is still unresolved.
+    pub fn delimiter_byte(&self) -> u8 {
+        match self.delimiter {
+            Delimiter::Byte(b) => b,
+            Delimiter::Auto => b',',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delimiter {
+    Byte(u8),
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum RecordTerminator {
+    #[default]
+    Crlf,
+    Byte(u8),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub enum InputEncoding {
+    #[default]
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    #[serde(rename = "windows-1252", alias = "cp1252")]
+    Windows1252,
+    #[serde(rename = "latin1", alias = "iso-8859-1")]
+    Latin1,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    // BOM decides (UTF-8 / UTF-16LE / UTF-16BE); no BOM means UTF-8
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HeaderMatching {
+    #[default]
+    Exact,
+    CaseInsensitive,
+    // Case-insensitive, ignoring whitespace, punctuation and underscores
+    Normalized,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnOrder {
+    // Matched by name only
+    #[default]
+    Any,
+    // Input columns are exactly the schema columns, in schema order
+    // (optional columns included)
+    Strict,
+    // All schema columns come first, in schema order; extra columns may follow
+    Prefix,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateHeaders {
+    #[default]
+    First,
+    Last,
+    // Every copy is validated; the first one is used for output, rules and keys
+    All,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UniqueMode {
+    // Keep every key: exact, memory grows with key size
+    #[default]
+    Exact,
+    // Keep a 64-bit hash per key: fixed cost per row, tiny false-positive risk
+    Hashed,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RaggedRows {
+    // Missing fields read as empty; extra fields only matter with failOnExtraColumns
+    #[default]
+    Ignore,
+    // RowTooShort / RowTooLong, and the rest of the row is not checked
+    Reject,
+    // Short rows get empty fields (so required columns fail); long rows are rejected
+    Pad,
+    // Long rows lose the extra fields; short rows are rejected
+    Truncate,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InjectionPolicy {
+    #[default]
+    Off,
+    // FormulaInjection / ControlCharacter errors
+    Flag,
+    // No errors; the normalized output gets a leading ' and loses control characters
+    Neutralize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SheetSelector {
+    Index(usize),
+    Name(String),
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -49,23 +404,126 @@ pub struct Schema {
 pub struct ColumnSpec {
     pub name: String,
 
+    // Other header names accepted for this column
+    #[serde(default)]
+    pub aliases: Vec<String>,
+
     #[serde(default)]
     pub required: bool,
 
     #[serde(rename = "type")]
     pub col_type: ColumnType,
 
+    // Length bounds on the trimmed value, counted in `length_unit`
+    // (minLen/exactLen do not apply to empty values)
     #[serde(default)]
     pub max_len: Option<usize>,
 
+    #[serde(default)]
+    pub min_len: Option<usize>,
+
+    #[serde(default)]
+    pub exact_len: Option<usize>,
+
+    #[serde(default)]
+    pub length_unit: LengthUnit,
+
+    // Every character must belong to all listed classes
+    #[serde(default, deserialize_with = "deserialize_char_classes")]
+    pub char_class: Vec<CharClass>,
+
     #[serde(default)]
     pub allowed: Vec<String>,
 
+    // Canonical value -> input synonyms that normalize to it; canonical
+    // values are allowed too
+    #[serde(default)]
+    pub allowed_map: BTreeMap<String, Vec<String>>,
+
+    #[serde(default)]
+    pub allowed_case_insensitive: bool,
+
+    // Name of a lookup set the value must belong to (loaded on the engine)
+    #[serde(default)]
+    pub lookup: Option<String>,
+
+    // Legacy name for `scale` (decimal places)
     #[serde(default)]
     pub precision: Option<u32>,
 
+    // Decimal places kept in the output (default 2)
+    #[serde(default)]
+    pub scale: Option<u32>,
+
+    // What to do with values that have more decimal places than `scale`
+    #[serde(default)]
+    pub scale_mode: ScaleMode,
+
+    // Max significant digits (integer + fractional) after scaling
+    #[serde(default)]
+    pub total_digits: Option<u32>,
+
+    // Built-in name ("ymd-dash", "dmy-slash", "mdy-slash") or a `time`
+    // format description ("[day].[month].[year]"); a list accepts any of them.
+    // Empty: "ymd-dash"
+    #[serde(default, deserialize_with = "deserialize_date_formats")]
+    pub date_format: Vec<String>,
+
+    // Regex the whole (trimmed) value must match
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    // Inclusive numeric bounds for int/decimal columns (exclusive when flagged)
+    #[serde(default, deserialize_with = "deserialize_bound")]
+    pub min: Option<Decimal>,
+
+    #[serde(default, deserialize_with = "deserialize_bound")]
+    pub max: Option<Decimal>,
+
+    #[serde(default)]
+    pub exclusive_min: bool,
+
+    #[serde(default)]
+    pub exclusive_max: bool,
+
+    // Inclusive bounds for date/datetime columns: ISO date/datetime, or
+    // relative to the engine's "now" ("today", "now", "-90d", "+1y")
+    #[serde(default)]
+    pub min_date: Option<String>,
+
+    #[serde(default)]
+    pub max_date: Option<String>,
+
+    // Value must not repeat across rows, compared in normalized form (empty
+    // values are not tracked)
+    #[serde(default)]
+    pub unique: bool,
+
+    // Required only when the condition holds / does not hold
+    #[serde(default)]
+    pub required_if: Option<Condition>,
+
+    #[serde(default)]
+    pub required_unless: Option<Condition>,
+
+    // Must be empty when the condition holds
+    #[serde(default)]
+    pub forbidden_if: Option<Condition>,
+
+    // Tokens accepted by bool columns (see ColumnSpec::bool_values)
     #[serde(default)]
-    pub date_format: Option<DateFormat>,
+    pub true_values: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub false_values: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub bool_case_sensitive: bool,
+
+    // Fixed offset ("UTC", "+02:00") assumed for datetime/time values
+    // that carry none; UTC when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
@@ -75,14 +533,143 @@ pub enum ColumnType {
     Int,
     Decimal,
     Date,
+    #[serde(alias = "boolean")]
+    Bool,
+    Datetime,
+    Time,
+}
+
+/// Test on another column of the same row: its trimmed text equals
+/// `equals`, is one of `in`, or (with neither) is non-empty.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Condition {
+    pub column: String,
+
+    #[serde(default)]
+    pub equals: Option<String>,
+
+    #[serde(default, rename = "in")]
+    pub one_of: Option<Vec<String>>,
+}
+
+impl ColumnSpec {
+    pub fn decimal_scale(&self) -> u32 {
+        self.scale.or(self.precision).unwrap_or(2)
+    }
+
+    /// Canonical allowed values: `allowed`, then the `allowedMap` keys.
+    pub fn allowed_values(&self) -> Vec<&str> {
+        self.allowed
+            .iter()
+            .chain(self.allowed_map.keys())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// (true tokens, false tokens) of a bool column, defaults filled in.
+    pub fn bool_values(&self) -> (Vec<&str>, Vec<&str>) {
+        (
+            tokens_or(&self.true_values, &["true", "t", "yes", "y", "1"]),
+            tokens_or(&self.false_values, &["false", "f", "no", "n", "0"]),
+        )
+    }
+}
+
+fn tokens_or<'a>(given: &'a Option<Vec<String>>, default: &[&'static str]) -> Vec<&'a str> {
+    match given {
+        Some(v) => v.iter().map(String::as_str).collect(),
+        None => default.to_vec(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    #[default]
+    Bytes, // UTF-8 bytes
+    Chars,     // Unicode scalar values
+    Graphemes, // user-perceived characters ("e" + combining accent is one)
+}
+
+impl LengthUnit {
+    pub fn count(self, s: &str) -> usize {
+        match self {
+            LengthUnit::Bytes => s.len(),
+            LengthUnit::Chars => s.chars().count(),
+            LengthUnit::Graphemes => s.graphemes(true).count(),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LengthUnit::Bytes => "bytes",
+            LengthUnit::Chars => "characters",
+            LengthUnit::Graphemes => "characters",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "camelCase")]
+pub enum CharClass {
+    Ascii,
+    Alphanumeric, // Unicode letters and digits
+    NoControl,
+}
+
+impl CharClass {
+    pub fn allows(self, c: char) -> bool {
+        match self {
+            CharClass::Ascii => c.is_ascii(),
+            CharClass::Alphanumeric => c.is_alphanumeric(),
+            CharClass::NoControl => !c.is_control(),
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            CharClass::Ascii => "ASCII",
+            CharClass::Alphanumeric => "alphanumeric",
+            CharClass::NoControl => "printable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScaleMode {
+    #[default]
+    Round, // half away from zero
+    Reject,   // ScaleExceeded if a non-zero digit would be dropped
+    Truncate, // drop extra digits (toward zero)
+}
+
+#[derive(Debug, Clone, Copy)]
 pub enum DateFormat {
-    YmdDash,   // "ymd-dash"
-    DmySlash,  // "dmy-slash"
-    MdySlash,  // "mdy-slash"
+    YmdDash,  // "ymd-dash"
+    DmySlash, // "dmy-slash"
+    MdySlash, // "mdy-slash"
+}
+
+impl DateFormat {
+    pub fn from_name(name: &str) -> Option<DateFormat> {
+        match name {
+            "ymd-dash" => Some(DateFormat::YmdDash),
+            "dmy-slash" => Some(DateFormat::DmySlash),
+            "mdy-slash" => Some(DateFormat::MdySlash),
+            _ => None,
+        }
+    }
+
+    /// Human-readable layout, e.g. "DD/MM/YYYY".
+    pub fn layout(self) -> &'static str {
+        match self {
+            DateFormat::YmdDash => "YYYY-MM-DD",
+            DateFormat::DmySlash => "DD/MM/YYYY",
+            DateFormat::MdySlash => "MM/DD/YYYY",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -92,3 +679,26 @@ pub struct Progress {
     pub errors_added: u32,
     pub done: bool,
 }
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DialectInfo {
+    pub encoding: Option<String>,
+    pub delimiter: Option<String>,
+    pub quote: String,
+    pub has_headers: bool,
+    pub sniffed: bool,
+    // Physical line (1-based) of the header record, or its sheet row for
+    // XLSX, once read
+    pub header_line: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderMatch {
+    pub input_index: usize,
+    pub input: String,
+    pub schema_index: Option<usize>,
+    pub column: Option<String>,
+    pub matched_by: Option<&'static str>, // "name" | "alias"
+}
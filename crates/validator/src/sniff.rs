@@ -0,0 +1,193 @@
+// Dialect sniffing for `delimiter: "auto"`.
+//
+// Works on a sample from the start of the input: picks the delimiter that
+// splits the sampled records into the most consistent (then widest) shape,
+// the quote character that actually wraps fields, and guesses whether the
+// first record is a header.
+
+use crate::header;
+use crate::schema::Schema;
+
+use csv_core::ReadRecordResult;
+
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+const QUOTES: [u8; 2] = [b'"', b'\''];
+const MAX_RECORDS: usize = 50;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sniffed {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub has_headers: bool,
+}
+
+/// `complete` means the sample is the whole input; otherwise the trailing
+/// partial line is ignored.
+pub fn sniff(sample: &[u8], complete: bool, schema: &Schema) -> Sniffed {
+    let sample = if complete {
+        sample
+    } else {
+        match sample.iter().rposition(|&b| b == b'\n' || b == b'\r') {
+            Some(i) => &sample[..=i],
+            None => sample,
+        }
+    };
+
+    // (consistency in permille, width, delimiter, quote)
+    let mut best: Option<(usize, usize, u8, u8)> = None;
+    for &delimiter in &DELIMITERS {
+        let quote = pick_quote(sample, delimiter);
+        let widths = record_widths(sample, schema, delimiter, quote, MAX_RECORDS);
+        if widths.is_empty() {
+            continue;
+        }
+
+        let (width, freq) = most_common(&widths);
+        if width < 2 {
+            continue;
+        }
+        let consistency = freq * 1000 / widths.len();
+        if best.is_none_or(|(c, w, _, _)| (consistency, width) > (c, w)) {
+            best = Some((consistency, width, delimiter, quote));
+        }
+    }
+
+    let (delimiter, quote) = match best {
+        Some((_, _, d, q)) => (d, q),
+        None => (b',', schema.quote),
+    };
+
+    let has_headers = schema
+        .has_headers
+        .unwrap_or_else(|| looks_like_header(sample, schema, delimiter, quote));
+
+    Sniffed {
+        delimiter,
+        quote,
+        has_headers,
+    }
+}
+
+// A quote "wraps" a field when it opens right after a delimiter/line start
+// and closes right before a delimiter/line end. Prefer ' only when it does
+// that more often than ".
+fn pick_quote(sample: &[u8], delimiter: u8) -> u8 {
+    let is_boundary =
+        |b: Option<&u8>| matches!(b, None | Some(b'\n') | Some(b'\r')) || b == Some(&delimiter);
+
+    let count = |q: u8| {
+        (0..sample.len())
+            .filter(|&i| sample[i] == q)
+            .filter(|&i| {
+                let before = if i == 0 { None } else { sample.get(i - 1) };
+                is_boundary(before) || is_boundary(sample.get(i + 1))
+            })
+            .count()
+    };
+
+    if count(b'\'') > count(b'"') {
+        QUOTES[1]
+    } else {
+        QUOTES[0]
+    }
+}
+
+/// Field count of each of the first `max` records.
+fn record_widths(
+    sample: &[u8],
+    schema: &Schema,
+    delimiter: u8,
+    quote: u8,
+    max: usize,
+) -> Vec<usize> {
+    let mut widths = Vec::new();
+    each_record(sample, schema, delimiter, quote, max, |_, ends| {
+        widths.push(ends.len())
+    });
+    widths
+}
+
+fn each_record<F>(sample: &[u8], schema: &Schema, delimiter: u8, quote: u8, max: usize, mut f: F)
+where
+    F: FnMut(&[u8], &[usize]),
+{
+    let mut rdr = crate::build_reader_with(schema, delimiter, quote);
+    let mut out = vec![0u8; sample.len() + 1];
+    let mut ends = vec![0usize; 1024];
+    let mut input = sample;
+    let mut seen = 0usize;
+
+    while seen < max {
+        let (res, nin, nout, nends) = rdr.read_record(input, &mut out, &mut ends);
+        input = &input[nin..];
+        match res {
+            ReadRecordResult::Record => {
+                f(&out[..nout], &ends[..nends]);
+                seen += 1;
+            }
+            ReadRecordResult::InputEmpty => {
+                // Sample exhausted: flush a trailing record without terminator
+                let (res, _, nout, nends) = rdr.read_record(&[], &mut out, &mut ends);
+                if let ReadRecordResult::Record = res {
+                    f(&out[..nout], &ends[..nends]);
+                }
+                return;
+            }
+            // Sample-sized output can't fill up; too many fields is not a
+            // plausible dialect either way
+            ReadRecordResult::OutputFull | ReadRecordResult::OutputEndsFull => return,
+            ReadRecordResult::End => return,
+        }
+    }
+}
+
+fn most_common(widths: &[usize]) -> (usize, usize) {
+    let mut best = (0usize, 0usize);
+    for &w in widths {
+        let freq = widths.iter().filter(|&&x| x == w).count();
+        if freq > best.1 || (freq == best.1 && w > best.0) {
+            best = (w, freq);
+        }
+    }
+    best
+}
+
+// Header if the first record names a schema column, or if it is text where
+// the second record has numbers.
+fn looks_like_header(sample: &[u8], schema: &Schema, delimiter: u8, quote: u8) -> bool {
+    let mut rows: Vec<Vec<String>> = Vec::with_capacity(2);
+    each_record(sample, schema, delimiter, quote, 2, |record, ends| {
+        let mut start = 0usize;
+        let fields = ends
+            .iter()
+            .map(|&end| {
+                let f = String::from_utf8_lossy(&record[start..end])
+                    .trim()
+                    .to_string();
+                start = end;
+                f
+            })
+            .collect();
+        rows.push(fields);
+    });
+
+    let first = match rows.first() {
+        Some(r) => r,
+        None => return false,
+    };
+    let names_column = first
+        .iter()
+        .any(|f| header::names_any_column(f.trim_start_matches('\u{feff}'), schema));
+    if names_column {
+        return true;
+    }
+
+    let is_number = |s: &str| !s.is_empty() && s.parse::<f64>().is_ok();
+    match rows.get(1) {
+        Some(second) => first
+            .iter()
+            .zip(second)
+            .any(|(a, b)| !a.is_empty() && !is_number(a) && is_number(b)),
+        None => false,
+    }
+}
@@ -0,0 +1,289 @@
+// Structural checks on the CSV byte stream. csv-core recovers from
+// malformed input without saying so (a stray quote just becomes data, an
+// unclosed quote swallows the rest of the file), so the bytes it consumes
+// are replayed through the same quoting rules here to notice when that
+// happens.
+
+use crate::schema::{RecordTerminator, Schema};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ProblemKind {
+    // Quote inside an unquoted field, or a closing quote followed by more data
+    UnexpectedQuote,
+    // Quoted field still open at end of input (offset of its opening quote)
+    UnterminatedQuote,
+    FieldTooLarge,
+    RecordTooLarge,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    // Input column (field index within the record)
+    pub field: usize,
+    // Byte offset into the UTF-8 input as parsed
+    pub offset: u64,
+    // Physical line where the record holding the problem starts
+    pub line: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    RecordStart,
+    Comment,
+    FieldStart,
+    Unquoted,
+    Quoted,
+    QuotedEscape,
+    // Quote seen inside a quoted field: doubled quote or the closing one
+    QuoteInQuoted,
+    AfterQuoted,
+}
+
+/// Physical line breaks: "\n", "\r\n" (counted once) and a lone "\r" with
+/// the CRLF terminator, the terminator byte otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineBreaks {
+    after_cr: bool,
+}
+
+impl LineBreaks {
+    /// Whether `b` ends a line; feed every byte, in order.
+    pub fn ends_line(&mut self, b: u8, terminator: RecordTerminator) -> bool {
+        match terminator {
+            RecordTerminator::Crlf => {
+                let after_cr = std::mem::replace(&mut self.after_cr, b == b'\r');
+                b == b'\r' || (b == b'\n' && !after_cr)
+            }
+            RecordTerminator::Byte(t) => b == t,
+        }
+    }
+}
+
+fn is_term(b: u8, terminator: RecordTerminator) -> bool {
+    match terminator {
+        RecordTerminator::Crlf => b == b'\n' || b == b'\r',
+        RecordTerminator::Byte(t) => b == t,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    offset: u64,
+    // Physical line (1-based) of the next byte, and of the current record
+    line: u64,
+    breaks: LineBreaks,
+    record_line: Option<u64>,
+    state: State,
+    field: usize,
+    field_len: usize,
+    record_len: usize,
+    // Opening quote of the current quoted field, and the last quote seen in it
+    opened_at: u64,
+    quote_at: u64,
+    // At most one report of each kind per field / record
+    quote_flagged: bool,
+    field_flagged: bool,
+    record_flagged: bool,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Scanner {
+            offset: 0,
+            line: 1,
+            breaks: LineBreaks::default(),
+            record_line: None,
+            state: State::RecordStart,
+            field: 0,
+            field_len: 0,
+            record_len: 0,
+            opened_at: 0,
+            quote_at: 0,
+            quote_flagged: false,
+            field_flagged: false,
+            record_flagged: false,
+        }
+    }
+}
+
+impl Scanner {
+    /// Account for `consumed` bytes taken by csv-core.
+    pub fn scan(&mut self, consumed: &[u8], schema: &Schema, problems: &mut Vec<Problem>) {
+        let quote = schema.quote;
+        let delimiter = schema.delimiter_byte();
+        let is_term = |b: u8| is_term(b, schema.terminator);
+
+        for &b in consumed {
+            let at = self.offset;
+            self.offset += 1;
+            let line = self.line;
+            if self.breaks.ends_line(b, schema.terminator) {
+                self.line += 1;
+            }
+
+            if self.state == State::RecordStart {
+                if is_term(b) {
+                    continue;
+                }
+                if Some(b) == schema.comment {
+                    self.state = State::Comment;
+                    continue;
+                }
+                self.record_line = Some(line);
+                self.record_len = 0;
+                self.field = 0;
+                self.record_flagged = false;
+                self.start_field();
+            }
+
+            if self.state == State::Comment {
+                if is_term(b) {
+                    self.state = State::RecordStart;
+                }
+                continue;
+            }
+
+            // A quote after a quote in a quoted field: "" is a literal quote,
+            // anything else means the field was closed
+            let doubled = self.state == State::QuoteInQuoted && b == quote && schema.double_quote;
+            if self.state == State::QuoteInQuoted {
+                self.state = if doubled {
+                    State::Quoted
+                } else {
+                    State::AfterQuoted
+                };
+            }
+
+            match self.state {
+                _ if doubled => {}
+                State::FieldStart | State::Unquoted | State::AfterQuoted if b == delimiter => {
+                    self.field += 1;
+                    self.start_field();
+                    self.record_len += 1;
+                    continue;
+                }
+                State::FieldStart | State::Unquoted | State::AfterQuoted if is_term(b) => {
+                    self.state = State::RecordStart;
+                    continue;
+                }
+                State::FieldStart if b == quote => {
+                    self.state = State::Quoted;
+                    self.opened_at = at;
+                }
+                State::FieldStart => self.state = State::Unquoted,
+                State::Unquoted if b == quote => self.flag(problems, at),
+                State::AfterQuoted => {
+                    // `"ab"c`: csv-core keeps going as an unquoted field
+                    let closing = self.quote_at;
+                    self.flag(problems, closing);
+                    self.state = State::Unquoted;
+                }
+                State::Quoted if Some(b) == schema.escape => self.state = State::QuotedEscape,
+                State::Quoted if b == quote => {
+                    self.state = State::QuoteInQuoted;
+                    self.quote_at = at;
+                }
+                State::QuotedEscape => self.state = State::Quoted,
+                _ => {}
+            }
+
+            self.field_len += 1;
+            self.record_len += 1;
+            if schema
+                .max_field_bytes
+                .is_some_and(|max| self.field_len > max)
+                && !self.field_flagged
+            {
+                self.field_flagged = true;
+                problems.push(self.problem(ProblemKind::FieldTooLarge, at));
+            }
+            if schema
+                .max_record_bytes
+                .is_some_and(|max| self.record_len > max)
+                && !self.record_flagged
+            {
+                self.record_flagged = true;
+                problems.push(self.problem(ProblemKind::RecordTooLarge, at));
+            }
+        }
+    }
+
+    /// Drop up to `lines` whole lines from the start of `input` ahead of
+    /// csv-core (`skipRows`), counting them off. Only positions move; returns
+    /// how many bytes were dropped.
+    pub fn skip_lines(&mut self, input: &[u8], lines: &mut usize, schema: &Schema) -> usize {
+        let mut cut = 0;
+        while *lines > 0 && cut < input.len() {
+            if self.breaks.ends_line(input[cut], schema.terminator) {
+                self.line += 1;
+                *lines -= 1;
+            }
+            cut += 1;
+        }
+        self.offset += cut as u64;
+        cut
+    }
+
+    /// Blank out the comment lines at the start of `ahead` (the bytes
+    /// csv-core is about to see), so it skips them as empty lines: csv-core
+    /// only ever ends a comment on "\n", and not even that with a custom
+    /// terminator. Lengths are kept, so offsets and lines still follow the
+    /// original input.
+    pub fn blank_comments(&self, ahead: &mut [u8], schema: &Schema) {
+        let blank = match schema.terminator {
+            RecordTerminator::Crlf => b'\n',
+            RecordTerminator::Byte(t) => t,
+        };
+        let mut state = self.state;
+        for b in ahead {
+            match state {
+                State::RecordStart if is_term(*b, schema.terminator) => {}
+                State::RecordStart if Some(*b) == schema.comment => state = State::Comment,
+                State::Comment if is_term(*b, schema.terminator) => state = State::RecordStart,
+                State::Comment => {}
+                _ => return,
+            }
+            *b = blank;
+        }
+    }
+
+    /// Line where the most recent record started (None before any record).
+    pub fn record_line(&self) -> Option<u64> {
+        self.record_line
+    }
+
+    /// End of input: a quoted field that never closed.
+    pub fn finish(&mut self) -> Option<Problem> {
+        match self.state {
+            State::Quoted | State::QuotedEscape => {
+                self.state = State::RecordStart;
+                Some(self.problem(ProblemKind::UnterminatedQuote, self.opened_at))
+            }
+            _ => None,
+        }
+    }
+
+    fn start_field(&mut self) {
+        self.state = State::FieldStart;
+        self.field_len = 0;
+        self.quote_flagged = false;
+        self.field_flagged = false;
+    }
+
+    fn flag(&mut self, problems: &mut Vec<Problem>, at: u64) {
+        if !self.quote_flagged {
+            self.quote_flagged = true;
+            problems.push(self.problem(ProblemKind::UnexpectedQuote, at));
+        }
+    }
+
+    fn problem(&self, kind: ProblemKind, offset: u64) -> Problem {
+        Problem {
+            kind,
+            field: self.field,
+            offset,
+            line: self.record_line.unwrap_or(self.line),
+        }
+    }
+}
@@ -0,0 +1,311 @@
+// Datetime and time-of-day columns.
+//
+// Accepted input (ISO 8601 extended format):
+//   datetime: YYYY-MM-DD(T| )HH:MM[:SS[.fraction]][offset]
+//   time:     HH:MM[:SS[.fraction]][offset]
+//   offset:   Z | +HH:MM | +HHMM | +HH (or -)
+// Values without an offset are taken to be in the column's `timezone`
+// (a fixed offset, UTC by default). Output is always UTC, RFC 3339 style.
+//
+// Also resolves `minDate`/`maxDate` bounds, absolute or relative to "now".
+
+use crate::schema::DateFormat;
+
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+/// Parse a `timezone` setting: "UTC", "Z" or a fixed offset like "+02:00".
+pub fn parse_timezone(s: &str) -> Option<UtcOffset> {
+    if s.eq_ignore_ascii_case("utc") {
+        return Some(UtcOffset::UTC);
+    }
+    match parse_offset(s.as_bytes())? {
+        (offset, []) => Some(offset),
+        _ => None,
+    }
+}
+
+pub fn parse_datetime(s: &str, default_offset: UtcOffset) -> Option<OffsetDateTime> {
+    let b = s.as_bytes();
+    if b.len() < 16 || !matches!(b[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    let date = parse_iso_date(&b[..10])?;
+    let (time, offset) = parse_time_offset(&b[11..])?;
+    Some(
+        PrimitiveDateTime::new(date, time)
+            .assume_offset(offset.unwrap_or(default_offset))
+            .to_offset(UtcOffset::UTC),
+    )
+}
+
+/// Time of day converted to UTC (wrapping around midnight).
+pub fn parse_time(s: &str, default_offset: UtcOffset) -> Option<Time> {
+    let (time, offset) = parse_time_offset(s.as_bytes())?;
+    let offset = offset.unwrap_or(default_offset);
+
+    let secs =
+        i64::from(time.hour()) * 3600 + i64::from(time.minute()) * 60 + i64::from(time.second())
+            - i64::from(offset.whole_seconds());
+    let secs = secs.rem_euclid(86_400);
+    Time::from_hms_nano(
+        (secs / 3600) as u8,
+        (secs / 60 % 60) as u8,
+        (secs % 60) as u8,
+        time.nanosecond(),
+    )
+    .ok()
+}
+
+/// "2024-01-31T08:30:00Z" (fraction only when non-zero).
+pub fn format_datetime(dt: OffsetDateTime) -> String {
+    dt.format(&Rfc3339).unwrap_or_default()
+}
+
+/// "08:30:00Z" / "08:30:00.25Z".
+pub fn format_time(t: Time) -> String {
+    let mut out = format!("{:02}:{:02}:{:02}", t.hour(), t.minute(), t.second());
+    if t.nanosecond() != 0 {
+        let frac = format!("{:09}", t.nanosecond());
+        out.push('.');
+        out.push_str(frac.trim_end_matches('0'));
+    }
+    out.push('Z');
+    out
+}
+
+// YYYY-MM-DD, fixed width
+fn parse_iso_date(b: &[u8]) -> Option<Date> {
+    if b.len() != 10 || b[4] != b'-' || b[7] != b'-' {
+        return None;
+    }
+    let y = digits(&b[0..4])?;
+    let m = digits(&b[5..7])?;
+    let d = digits(&b[8..10])?;
+    Date::from_calendar_date(y as i32, Month::try_from(m as u8).ok()?, d as u8).ok()
+}
+
+fn parse_time_offset(b: &[u8]) -> Option<(Time, Option<UtcOffset>)> {
+    if b.len() < 5 || b[2] != b':' {
+        return None;
+    }
+    let h = digits(&b[0..2])?;
+    let m = digits(&b[3..5])?;
+    let mut rest = &b[5..];
+
+    let mut sec = 0;
+    let mut nano = 0;
+    if rest.first() == Some(&b':') {
+        sec = digits(rest.get(1..3)?)?;
+        rest = &rest[3..];
+
+        if matches!(rest.first(), Some(b'.') | Some(b',')) {
+            let n = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if n == 0 || n > 9 {
+                return None;
+            }
+            nano = digits(&rest[1..=n])? * 10u32.pow(9 - n as u32);
+            rest = &rest[1 + n..];
+        }
+    }
+    let time = Time::from_hms_nano(h as u8, m as u8, sec as u8, nano).ok()?;
+
+    if rest.is_empty() {
+        return Some((time, None));
+    }
+    match parse_offset(rest)? {
+        (offset, []) => Some((time, Some(offset))),
+        _ => None,
+    }
+}
+
+// Z | ±HH[[:]MM]; returns the offset and what follows it
+fn parse_offset(b: &[u8]) -> Option<(UtcOffset, &[u8])> {
+    let sign = match b.first()? {
+        b'Z' | b'z' => return Some((UtcOffset::UTC, &b[1..])),
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let h = digits(b.get(1..3)?)?;
+    let (m, rest) = match &b[3..] {
+        [b':', rest @ ..] => (digits(rest.get(..2)?)?, &rest[2..]),
+        [a, c, rest @ ..] if a.is_ascii_digit() => (digits(&[*a, *c])?, rest),
+        rest => (0, rest),
+    };
+    if h > 23 || m > 59 {
+        return None;
+    }
+    let offset = UtcOffset::from_hms(sign * h as i8, sign * m as i8, 0).ok()?;
+    Some((offset, rest))
+}
+
+// None also when the number does not fit a u32
+fn digits(b: &[u8]) -> Option<u32> {
+    if b.is_empty() || !b.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    b.iter().try_fold(0u32, |acc, &c| {
+        acc.checked_mul(10)?.checked_add(u32::from(c - b'0'))
+    })
+}
+
+/// A `minDate`/`maxDate` resolved against the engine's "now".
+#[derive(Debug, Clone, Copy)]
+pub enum DateBound {
+    // Whole calendar day ("2024-01-31", "today", "-90d")
+    Day(Date),
+    // Exact instant ("2024-01-31T12:00:00Z", "now", "now-1d")
+    Instant(OffsetDateTime),
+}
+
+impl DateBound {
+    /// Compare a date value with the bound (instants by their UTC day).
+    pub fn cmp_date(self, d: Date) -> std::cmp::Ordering {
+        match self {
+            DateBound::Day(b) => d.cmp(&b),
+            DateBound::Instant(b) => d.cmp(&b.date()),
+        }
+    }
+
+    /// Compare a (UTC) datetime value with the bound (days by the value's UTC day).
+    pub fn cmp_datetime(self, t: OffsetDateTime) -> std::cmp::Ordering {
+        match self {
+            DateBound::Day(b) => t.date().cmp(&b),
+            DateBound::Instant(b) => t.cmp(&b),
+        }
+    }
+}
+
+impl std::fmt::Display for DateBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateBound::Day(d) => write!(f, "{}", crate::format_date(*d, DateFormat::YmdDash)),
+            DateBound::Instant(t) => f.write_str(&format_datetime(*t)),
+        }
+    }
+}
+
+/// Parse an absolute bound (ISO date or datetime) or a relative one:
+/// `today` / `now`, optionally followed by `+N` / `-N` with unit d, w, m or y
+/// (a bare offset like "-90d" is relative to today). Relative bounds need `now`.
+pub fn parse_date_bound(s: &str, now: Option<OffsetDateTime>) -> Result<DateBound, String> {
+    let s = s.trim();
+    if let Some(d) = parse_iso_date(s.as_bytes()) {
+        return Ok(DateBound::Day(d));
+    }
+    if let Some(t) = parse_datetime(s, UtcOffset::UTC) {
+        return Ok(DateBound::Instant(t));
+    }
+
+    let lower = s.to_ascii_lowercase();
+    let (base_is_now, offset) = if let Some(rest) = lower.strip_prefix("today") {
+        (false, rest)
+    } else if let Some(rest) = lower.strip_prefix("now") {
+        (true, rest)
+    } else if lower.starts_with(['+', '-']) {
+        (false, lower.as_str())
+    } else {
+        return Err(format!("invalid date bound \"{s}\""));
+    };
+
+    let shift = match offset {
+        "" => None,
+        _ => Some(parse_shift(offset).ok_or_else(|| format!("invalid date bound \"{s}\""))?),
+    };
+
+    let now = now.ok_or_else(|| {
+        format!("date bound \"{s}\" is relative; the engine needs the current time (`now`)")
+    })?;
+    let now = now.to_offset(UtcOffset::UTC);
+
+    let shift_date = |d: Date| match shift {
+        Some(sh) => apply_shift(d, sh),
+        None => Some(d),
+    };
+    let out_of_range = || format!("date bound \"{s}\" is out of range");
+
+    if base_is_now {
+        let date = shift_date(now.date()).ok_or_else(out_of_range)?;
+        Ok(DateBound::Instant(now.replace_date(date)))
+    } else {
+        Ok(DateBound::Day(
+            shift_date(now.date()).ok_or_else(out_of_range)?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+// "+90d", "-1y"
+fn parse_shift(s: &str) -> Option<(i64, Unit)> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let unit = match rest.as_bytes().last()? {
+        b'd' => Unit::Day,
+        b'w' => Unit::Week,
+        b'm' => Unit::Month,
+        b'y' => Unit::Year,
+        _ => return None,
+    };
+    let n = digits(&rest.as_bytes()[..rest.len() - 1])?;
+    Some((sign * i64::from(n), unit))
+}
+
+fn apply_shift(d: Date, (n, unit): (i64, Unit)) -> Option<Date> {
+    match unit {
+        Unit::Day => d.checked_add(time::Duration::days(n)),
+        Unit::Week => d.checked_add(time::Duration::weeks(n)),
+        Unit::Month => add_months(d, n),
+        Unit::Year => add_months(d, n.checked_mul(12)?),
+    }
+}
+
+// Calendar months; the day is clamped (Jan 31 + 1m = Feb 28/29)
+fn add_months(d: Date, n: i64) -> Option<Date> {
+    let total = i64::from(d.year()) * 12 + i64::from(u8::from(d.month()) - 1) + n;
+    let year = i32::try_from(total.div_euclid(12)).ok()?;
+    let month = Month::try_from((total.rem_euclid(12) + 1) as u8).ok()?;
+    let day = d.day().min(time::util::days_in_month(month, year));
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> Option<OffsetDateTime> {
+        Some(OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap())
+    }
+
+    #[test]
+    fn relative_bounds_shift_from_today() {
+        let bound = parse_date_bound("-90d", now()).unwrap();
+        assert_eq!(bound.to_string(), "2023-08-16");
+        let bound = parse_date_bound("today+1m", now()).unwrap();
+        assert_eq!(bound.to_string(), "2023-12-14");
+    }
+
+    #[test]
+    fn oversized_shift_is_a_schema_error() {
+        for s in [
+            "+99999999999d",
+            "-4294967296w",
+            "today+99999999999999999999y",
+        ] {
+            assert!(parse_date_bound(s, now()).is_err(), "{s}");
+        }
+        // Fits a u32 but not the calendar
+        let err = parse_date_bound("+4294967295y", now()).unwrap_err();
+        assert!(err.contains("out of range"), "{err}");
+    }
+}
@@ -0,0 +1,95 @@
+// Input transcoding to UTF-8, ahead of dialect sniffing and csv-core.
+//
+// UTF-8 input never gets here (no copy on the fast path). Malformed byte
+// sequences in the source encoding become U+FFFD.
+
+use crate::schema::InputEncoding;
+
+use encoding_rs::{Decoder, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+pub struct Transcoder {
+    state: State,
+}
+
+enum State {
+    // "auto": waiting for enough bytes to look for a BOM
+    Sniffing(Vec<u8>),
+    Utf8,
+    // ISO-8859-1 is a 1:1 map onto U+0000..=U+00FF (encoding_rs would treat
+    // the label as windows-1252, which differs in 0x80..=0x9F)
+    Latin1,
+    Decode(Decoder),
+}
+
+impl Transcoder {
+    /// None for plain UTF-8: chunks can be parsed as-is.
+    pub fn new(encoding: InputEncoding) -> Option<Transcoder> {
+        let state = match encoding {
+            InputEncoding::Utf8 => return None,
+            InputEncoding::Auto => State::Sniffing(Vec::new()),
+            InputEncoding::Latin1 => State::Latin1,
+            InputEncoding::Windows1252 => {
+                State::Decode(WINDOWS_1252.new_decoder_with_bom_removal())
+            }
+            InputEncoding::Utf16Le => State::Decode(UTF_16LE.new_decoder_with_bom_removal()),
+            InputEncoding::Utf16Be => State::Decode(UTF_16BE.new_decoder_with_bom_removal()),
+        };
+        Some(Transcoder { state })
+    }
+
+    /// Name of the encoding in use; None while "auto" still waits for a BOM.
+    pub fn name(&self) -> Option<&'static str> {
+        match &self.state {
+            State::Sniffing(_) => None,
+            State::Utf8 => Some("utf-8"),
+            State::Latin1 => Some("latin1"),
+            State::Decode(d) => Some(match d.encoding().name() {
+                "UTF-16LE" => "utf-16le",
+                "UTF-16BE" => "utf-16be",
+                _ => "windows-1252",
+            }),
+        }
+    }
+
+    /// Transcode `chunk` into `out` (cleared first). `last` flushes any
+    /// partial code unit held back from earlier chunks.
+    pub fn transcode(&mut self, chunk: &[u8], last: bool, out: &mut Vec<u8>) {
+        out.clear();
+
+        if let State::Sniffing(pending) = &mut self.state {
+            pending.extend_from_slice(chunk);
+            if pending.len() < 3 && !last {
+                return;
+            }
+            let pending = std::mem::take(pending);
+            self.state = match pending.as_slice() {
+                [0xFF, 0xFE, ..] => State::Decode(UTF_16LE.new_decoder_with_bom_removal()),
+                [0xFE, 0xFF, ..] => State::Decode(UTF_16BE.new_decoder_with_bom_removal()),
+                // UTF-8 BOM (if any) is left for the header parser to strip
+                _ => State::Utf8,
+            };
+            return self.transcode(&pending, last, out);
+        }
+
+        match &mut self.state {
+            State::Sniffing(_) => unreachable!(),
+            State::Utf8 => out.extend_from_slice(chunk),
+            State::Latin1 => {
+                out.reserve(chunk.len() * 2);
+                for &b in chunk {
+                    let mut buf = [0u8; 2];
+                    out.extend_from_slice((b as char).encode_utf8(&mut buf).as_bytes());
+                }
+            }
+            State::Decode(decoder) => {
+                let cap = decoder
+                    .max_utf8_buffer_length(chunk.len())
+                    .unwrap_or(chunk.len() * 3 + 16);
+                out.resize(cap, 0);
+                // Sized for the worst case, so the whole chunk is consumed
+                let (_, _, written, _) = decoder.decode_to_utf8(chunk, out, last);
+                out.truncate(written);
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Cross-row key tracking for `unique` columns and the schema primary key.
+
+use crate::schema::{Schema, UniqueMode};
+
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+pub enum KeySet {
+    Exact(HashMap<Box<[u8]>, u32>),
+    Hashed(HashMap<u64, u32>),
+}
+
+impl KeySet {
+    pub fn new(mode: UniqueMode) -> KeySet {
+        match mode {
+            UniqueMode::Exact => KeySet::Exact(HashMap::new()),
+            UniqueMode::Hashed => KeySet::Hashed(HashMap::new()),
+        }
+    }
+
+    /// Remember `key` as seen on `row`.
+    /// Returns the row of the first occurrence if the key was already seen.
+    pub fn insert(&mut self, key: &[u8], row: u32) -> Option<u32> {
+        match self {
+            KeySet::Exact(seen) => match seen.get(key) {
+                Some(&first) => Some(first),
+                None => {
+                    seen.insert(key.into(), row);
+                    None
+                }
+            },
+            KeySet::Hashed(seen) => match seen.entry(hash_key(key)) {
+                Entry::Occupied(e) => Some(*e.get()),
+                Entry::Vacant(e) => {
+                    e.insert(row);
+                    None
+                }
+            },
+        }
+    }
+}
+
+// DefaultHasher::new() uses fixed keys, so results are stable across runs
+fn hash_key(key: &[u8]) -> u64 {
+    let mut h = DefaultHasher::new();
+    h.write(key);
+    h.finish()
+}
+
+/// Append one component of a composite key. Length-prefixed so that
+/// ("ab", "c") and ("a", "bc") never produce the same key.
+pub fn push_key_part(key: &mut Vec<u8>, part: &[u8]) {
+    key.extend_from_slice(&(part.len() as u32).to_le_bytes());
+    key.extend_from_slice(part);
+}
+
+/// Resolve `schema.primary_key` names to schema column indices.
+pub fn resolve_primary_key(schema: &Schema) -> Result<Vec<usize>, String> {
+    schema
+        .primary_key
+        .iter()
+        .map(|name| {
+            schema
+                .columns
+                .iter()
+                .position(|c| c.name == *name)
+                .ok_or_else(|| format!("primaryKey column \"{name}\" is not in the schema"))
+        })
+        .collect()
+}
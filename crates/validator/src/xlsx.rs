@@ -0,0 +1,450 @@
+// crates/validator/src/xlsx.rs
+//
+// Minimal XLSX (Office Open XML) reader.
+//
+// Only what the validator needs: resolve one worksheet, load the shared
+// string table, then stream the sheet XML row by row. Styles, formulas and
+// number formats are ignored; cells are reported with their stored value.
+
+use crate::schema::SheetSelector;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader as XmlReader;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
+use zip::ZipArchive;
+
+/// One non-empty cell of a worksheet row.
+#[derive(Debug, Clone)]
+pub struct Cell {
+    /// Zero-based column index (A = 0)
+    pub col: usize,
+    /// True when the cell holds a number (dates are stored as serial numbers)
+    pub numeric: bool,
+    pub value: String,
+}
+
+/// Workbook-level facts the caller needs to interpret cells.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkbookInfo {
+    /// Serial dates count from 1904-01-01 instead of 1899-12-30
+    pub date1904: bool,
+}
+
+/// Stream the selected worksheet, calling `on_row` once per non-empty row
+/// with its 1-based sheet row number (as Excel shows it) and its cells in
+/// column order. Returning `false` from `on_row` stops early.
+pub fn read_rows<F>(
+    bytes: &[u8],
+    selector: Option<&SheetSelector>,
+    mut on_row: F,
+) -> Result<WorkbookInfo, String>
+where
+    F: FnMut(u32, &[Cell], WorkbookInfo) -> bool,
+{
+    let mut archive =
+        ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("not a zip archive: {e}"))?;
+
+    let (sheets, info) = read_workbook(&mut archive)?;
+    let rels = read_relationships(&mut archive)?;
+
+    let sheet_idx = match selector {
+        None => 0,
+        Some(SheetSelector::Index(i)) => *i,
+        Some(SheetSelector::Name(name)) => sheets
+            .iter()
+            .position(|s| s.name == *name)
+            .ok_or_else(|| format!("sheet \"{name}\" not found"))?,
+    };
+    let sheet = sheets.get(sheet_idx).ok_or_else(|| {
+        format!(
+            "sheet index {sheet_idx} out of range ({} sheets)",
+            sheets.len()
+        )
+    })?;
+
+    // Resolve the sheet part through the workbook relationships; fall back to
+    // the conventional part name when the rels part is missing.
+    let sheet_path = rels
+        .iter()
+        .find(|(id, _)| *id == sheet.rel_id)
+        .map(|(_, target)| resolve_target(target))
+        .unwrap_or_else(|| format!("xl/worksheets/sheet{}.xml", sheet_idx + 1));
+
+    let shared = read_shared_strings(&mut archive)?;
+
+    let file = archive
+        .by_name(&sheet_path)
+        .map_err(|e| format!("cannot open {sheet_path}: {e}"))?;
+    let mut xml = XmlReader::from_reader(BufReader::new(file));
+
+    stream_sheet(&mut xml, &shared, |row, cells| on_row(row, cells, info))?;
+    Ok(info)
+}
+
+struct SheetEntry {
+    name: String,
+    rel_id: String,
+}
+
+fn read_workbook<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<(Vec<SheetEntry>, WorkbookInfo), String> {
+    let file = archive
+        .by_name("xl/workbook.xml")
+        .map_err(|e| format!("missing xl/workbook.xml: {e}"))?;
+    let mut xml = XmlReader::from_reader(BufReader::new(file));
+
+    let mut sheets = Vec::new();
+    let mut info = WorkbookInfo::default();
+    let mut buf = Vec::new();
+
+    loop {
+        match xml.read_event_into(&mut buf).map_err(xml_err)? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"sheet" => {
+                    let name = attr(&e, b"name")?.unwrap_or_default();
+                    let rel_id = attr(&e, b"id")?.unwrap_or_default();
+                    sheets.push(SheetEntry { name, rel_id });
+                }
+                b"workbookPr" => {
+                    let v = attr(&e, b"date1904")?;
+                    info.date1904 = matches!(v.as_deref(), Some("1") | Some("true"));
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    if sheets.is_empty() {
+        return Err("workbook has no sheets".to_string());
+    }
+    Ok((sheets, info))
+}
+
+fn read_relationships<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Vec<(String, String)>, String> {
+    let file = match archive.by_name("xl/_rels/workbook.xml.rels") {
+        Ok(f) => f,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut xml = XmlReader::from_reader(BufReader::new(file));
+
+    let mut rels = Vec::new();
+    let mut buf = Vec::new();
+
+    loop {
+        match xml.read_event_into(&mut buf).map_err(xml_err)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if let (Some(id), Some(target)) = (attr(&e, b"Id")?, attr(&e, b"Target")?) {
+                    rels.push((id, target));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(rels)
+}
+
+// Relationship targets are relative to xl/ unless they start with '/'
+fn resolve_target(target: &str) -> String {
+    match target.strip_prefix('/') {
+        Some(abs) => abs.to_string(),
+        None => format!("xl/{target}"),
+    }
+}
+
+fn read_shared_strings<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Vec<String>, String> {
+    let file = match archive.by_name("xl/sharedStrings.xml") {
+        Ok(f) => f,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut xml = XmlReader::from_reader(BufReader::new(file));
+
+    let mut strings = Vec::new();
+    let mut cur = String::new();
+    let mut in_t = false;
+    // Phonetic runs (<rPh>) carry ruby text that is not part of the value
+    let mut in_rph = false;
+    let mut buf = Vec::new();
+
+    loop {
+        match xml.read_event_into(&mut buf).map_err(xml_err)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"si" => cur.clear(),
+                b"t" => in_t = true,
+                b"rPh" => in_rph = true,
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"si" => strings.push(String::new()),
+            Event::Text(t) if in_t && !in_rph => cur.push_str(&t.unescape().map_err(xml_err)?),
+            Event::CData(t) if in_t && !in_rph => cur.push_str(&String::from_utf8_lossy(&t)),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"si" => strings.push(std::mem::take(&mut cur)),
+                b"t" => in_t = false,
+                b"rPh" => in_rph = false,
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(strings)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CellType {
+    Number,
+    SharedString,
+    InlineString,
+    FormulaString,
+    Bool,
+    Error,
+}
+
+fn stream_sheet<B, F>(
+    xml: &mut XmlReader<B>,
+    shared: &[String],
+    mut on_row: F,
+) -> Result<(), String>
+where
+    B: BufRead,
+    F: FnMut(u32, &[Cell]) -> bool,
+{
+    let mut buf = Vec::new();
+    let mut cells: Vec<Cell> = Vec::with_capacity(64);
+
+    let mut in_sheet_data = false;
+    // Rows without cells are left out of the XML, so follow `r` when given
+    let mut row_num = 0u32;
+    let mut cell_col = 0usize;
+    let mut cell_type = CellType::Number;
+    let mut next_col = 0usize;
+    let mut value = String::new();
+    let mut in_v = false;
+    let mut in_is_t = false;
+    let mut in_is = false;
+
+    loop {
+        match xml.read_event_into(&mut buf).map_err(xml_err)? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"sheetData" => in_sheet_data = true,
+                b"row" if in_sheet_data => {
+                    row_num = row_number(&e, row_num)?;
+                    cells.clear();
+                    next_col = 0;
+                }
+                b"c" if in_sheet_data => {
+                    (cell_col, cell_type) = cell_header(&e, next_col)?;
+                    value.clear();
+                }
+                b"v" => in_v = true,
+                b"is" => in_is = true,
+                b"t" if in_is => in_is_t = true,
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                // An empty <row/> has no cells; an empty <c/> has no value
+                b"row" if in_sheet_data => row_num = row_number(&e, row_num)?,
+                b"c" if in_sheet_data => {
+                    let (col, _) = cell_header(&e, next_col)?;
+                    next_col = col + 1;
+                }
+                _ => {}
+            },
+            Event::Text(t) if in_v || in_is_t => value.push_str(&t.unescape().map_err(xml_err)?),
+            Event::CData(t) if in_v || in_is_t => value.push_str(&String::from_utf8_lossy(&t)),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"v" => in_v = false,
+                b"is" => in_is = false,
+                b"t" => in_is_t = false,
+                b"c" if in_sheet_data => {
+                    next_col = cell_col + 1;
+                    if let Some(cell) = finish_cell(cell_col, cell_type, &value, shared) {
+                        cells.push(cell);
+                    }
+                }
+                b"row" if in_sheet_data && !cells.is_empty() && !on_row(row_num, &cells) => {
+                    return Ok(());
+                }
+                b"sheetData" => return Ok(()),
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
+
+fn row_number(e: &BytesStart<'_>, prev: u32) -> Result<u32, String> {
+    match attr(e, b"r")? {
+        Some(r) => r
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid row number \"{r}\"")),
+        None => Ok(prev.saturating_add(1)),
+    }
+}
+
+// Column XFD, the last one a sheet can have
+const MAX_COL: usize = 16_383;
+
+fn cell_header(e: &BytesStart<'_>, next_col: usize) -> Result<(usize, CellType), String> {
+    let col = match attr(e, b"r")? {
+        Some(r) => column_index(&r)
+            .filter(|&c| c <= MAX_COL)
+            .ok_or_else(|| format!("invalid cell reference \"{r}\""))?,
+        None if next_col > MAX_COL => return Err("row has cells past column XFD".to_string()),
+        None => next_col,
+    };
+    let ty = match attr(e, b"t")?.as_deref() {
+        None | Some("n") => CellType::Number,
+        Some("s") => CellType::SharedString,
+        Some("inlineStr") => CellType::InlineString,
+        Some("str") => CellType::FormulaString,
+        Some("b") => CellType::Bool,
+        Some("e") => CellType::Error,
+        // Strict OOXML "d" (ISO date text) and unknown types: keep the text
+        Some(_) => CellType::FormulaString,
+    };
+    Ok((col, ty))
+}
+
+fn finish_cell(col: usize, ty: CellType, raw: &str, shared: &[String]) -> Option<Cell> {
+    let (numeric, value) = match ty {
+        CellType::Number => {
+            if raw.is_empty() {
+                return None;
+            }
+            (true, raw.to_string())
+        }
+        CellType::SharedString => {
+            let idx: usize = raw.trim().parse().ok()?;
+            (false, shared.get(idx)?.clone())
+        }
+        CellType::Bool => (
+            false,
+            if raw.trim() == "1" { "TRUE" } else { "FALSE" }.to_string(),
+        ),
+        CellType::InlineString | CellType::FormulaString | CellType::Error => {
+            (false, raw.to_string())
+        }
+    };
+    if value.is_empty() && ty != CellType::Number {
+        return None;
+    }
+    Some(Cell {
+        col,
+        numeric,
+        value,
+    })
+}
+
+/// "BC12" -> 54 (zero-based column of the cell reference)
+fn column_index(cell_ref: &str) -> Option<usize> {
+    let mut col = 0usize;
+    let mut seen = false;
+    for b in cell_ref.bytes() {
+        if b.is_ascii_alphabetic() {
+            col = col
+                .checked_mul(26)?
+                .checked_add((b.to_ascii_uppercase() - b'A' + 1) as usize)?;
+            seen = true;
+        } else {
+            break;
+        }
+    }
+    if seen {
+        Some(col - 1)
+    } else {
+        None
+    }
+}
+
+/// Date and time of day (to the second) of an Excel serial.
+pub fn serial_to_datetime(serial: f64, date1904: bool) -> Option<time::PrimitiveDateTime> {
+    let date = serial_to_date(serial_seconds(serial)?.div_euclid(86_400) as f64, date1904)?;
+    Some(date.with_time(serial_to_time(serial)?))
+}
+
+/// Time of day (to the second) of an Excel serial; the day part is ignored.
+pub fn serial_to_time(serial: f64) -> Option<time::Time> {
+    let secs = serial_seconds(serial)?.rem_euclid(86_400);
+    time::Time::from_hms(
+        (secs / 3600) as u8,
+        (secs / 60 % 60) as u8,
+        (secs % 60) as u8,
+    )
+    .ok()
+}
+
+// Serial of 9999-12-31, the last date Excel can represent
+const MAX_SERIAL: f64 = 2_958_465.0;
+
+fn valid_serial(serial: f64) -> bool {
+    serial.is_finite() && (0.0..MAX_SERIAL + 1.0).contains(&serial)
+}
+
+// Rounded to whole seconds: serials carry float noise ("12:00" = 0.49999...)
+fn serial_seconds(serial: f64) -> Option<i64> {
+    if !valid_serial(serial) {
+        return None;
+    }
+    Some((serial * 86_400.0).round() as i64)
+}
+
+/// Convert an Excel serial day number into a calendar date.
+/// The fractional (time-of-day) part is ignored; serials outside Excel's
+/// date range give None.
+pub fn serial_to_date(serial: f64, date1904: bool) -> Option<time::Date> {
+    if !valid_serial(serial) {
+        return None;
+    }
+    let days = serial.floor() as i64;
+
+    let (base, offset) = if date1904 {
+        (
+            time::Date::from_calendar_date(1904, time::Month::January, 1).ok()?,
+            days,
+        )
+    } else {
+        // 1900 system: serial 1 = 1900-01-01, and Lotus' phantom 1900-02-29
+        // occupies serial 60, so later serials are one day ahead.
+        match days {
+            0 => return None,
+            60 => return None,
+            1..=59 => (
+                time::Date::from_calendar_date(1899, time::Month::December, 31).ok()?,
+                days,
+            ),
+            _ => (
+                time::Date::from_calendar_date(1899, time::Month::December, 30).ok()?,
+                days,
+            ),
+        }
+    };
+
+    base.checked_add(time::Duration::days(offset))
+}
+
+fn attr(e: &BytesStart<'_>, local: &[u8]) -> Result<Option<String>, String> {
+    for a in e.attributes() {
+        let a = a.map_err(|err| format!("malformed XML attribute: {err}"))?;
+        if a.key.local_name().as_ref() == local {
+            return Ok(Some(a.unescape_value().map_err(xml_err)?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn xml_err(e: quick_xml::Error) -> String {
+    format!("malformed XML: {e}")
+}
@@ -1,8 +1,15 @@
-import type { Progress, PackedError,DecodedError } from "./types";
+import type {
+    Progress,
+    PackedError,
+    DecodedError,
+    Dialect,
+    HeaderMatch,
+    ErrorDetail,
+} from "./types";
 import { initValidatorWasm } from "./wasm";
 export { defaultWasmUrl } from "./wasmUrl";
 
-export type { Progress, PackedError, DecodedError };
+export type { Progress, PackedError, DecodedError, Dialect, HeaderMatch, ErrorDetail };
 
 let wasmReady: Promise<any> | null = null;
 
@@ -20,10 +27,22 @@ export class Engine {
     private engine: any;
     private constructor() {}
 
-    static async create(schema: object, maxErrors: number, emitNormalized: boolean) {
+    // `now` anchors relative minDate/maxDate bounds ("today", "-90d")
+    static async create(
+        schema: object,
+        maxErrors: number,
+        emitNormalized: boolean,
+        now?: Date | number
+    ) {
         const mod = await requireMod();
         const inst = new Engine();
-        inst.engine = new mod.ValidatorEngine(JSON.stringify(schema), maxErrors, emitNormalized);
+        const nowMs = now === undefined ? undefined : now.valueOf();
+        inst.engine = new mod.ValidatorEngine(
+            JSON.stringify(schema),
+            maxErrors,
+            emitNormalized,
+            nowMs
+        );
         return inst;
     }
 
@@ -32,13 +51,39 @@ export class Engine {
         return JSON.parse(json);
     }
 
+    pushXlsx(workbook: Uint8Array): Progress {
+        const json = this.engine.push_xlsx(workbook) as unknown as string;
+        return JSON.parse(json);
+    }
+
+    // Reference set for `lookup` columns: a CSV/newline list (first column
+    // used, no header), streamed like the data. Load before pushing data.
+    pushLookupChunk(name: string, chunk: Uint8Array, finalChunk: boolean): void {
+        this.engine.push_lookup_chunk(name, chunk, finalChunk);
+    }
+
+    pushLookupValues(name: string, values: string[]): void {
+        const enc = new TextEncoder();
+        const parts = values.map((v) => enc.encode(v));
+        const data = new Uint8Array(parts.reduce((n, p) => n + p.length, 0));
+        const ends = new Uint32Array(parts.length);
+        let at = 0;
+        parts.forEach((p, i) => {
+            data.set(p, at);
+            at += p.length;
+            ends[i] = at;
+        });
+        this.engine.push_lookup_packed(name, data, ends);
+    }
+
     takeErrors(max: number): PackedError[] {
         const raw = this.engine.take_errors_packed(max) as number[];
         const out: PackedError[] = [];
 
-        for (let i = 0; i < raw.length; i += 2) {
+        for (let i = 0; i < raw.length; i += 3) {
             const row = raw[i];
             const w1 = raw[i + 1];
+            const detail = raw[i + 2];
 
             // kind is stored in top bit (bit 31)
             const kind = (w1 >>> 31) & 1;
@@ -52,6 +97,7 @@ export class Engine {
                 row,
                 col,
                 code,
+                detail,
                 // optional: you can extend type to include kind
                 // kind,
             });
@@ -73,22 +119,47 @@ export class Engine {
         return mod.ValidatorEngine.error_code_to_string(code);
     }
 
+    dialect(): Dialect {
+        return JSON.parse(this.engine.dialect_json());
+    }
+
     inputColumns(): string[] {
         // new Rust method: input_columns_json()
         return JSON.parse(this.engine.input_columns_json());
     }
 
+    // RuleViolation's detail indexes into this
+    ruleMessages(): string[] {
+        return JSON.parse(this.engine.rule_messages_json());
+    }
+
+    headerMapping(): HeaderMatch[] {
+        return JSON.parse(this.engine.header_mapping_json());
+    }
+
+    // Opt-in: record value, position and an expected hint for every later error
+    enableErrorDetails(maxValueLen = 200): void {
+        this.engine.enable_error_details(maxValueLen);
+    }
+
+    // Same order as the packed errors
+    takeErrorDetails(max: number): ErrorDetail[] {
+        return JSON.parse(this.engine.take_error_details_json(max));
+    }
+
     takeErrorsDecoded(max: number): import("./types").DecodedError[] {
         const raw = this.engine.take_errors_packed(max) as number[];
 
         const schemaCols = this.schemaColumns();
         const inputCols = this.inputColumns();
+        const ruleMessages = this.ruleMessages();
 
         const out: import("./types").DecodedError[] = [];
 
-        for (let i = 0; i < raw.length; i += 2) {
+        for (let i = 0; i < raw.length; i += 3) {
             const row = raw[i];
             const w1 = raw[i + 1];
+            const detail = raw[i + 2];
 
             const kindBit = (w1 >>> 31) & 1;
             const colIndex = (w1 >>> 8) & 0x7fffff;
@@ -107,7 +178,15 @@ export class Engine {
                 colIndex,
                 colKind,
                 columnName,
-                message: Engine.makeMessage(row, codeString, columnName),
+                detail,
+                message: Engine.makeMessage(
+                    row,
+                    codeString,
+                    columnName,
+                    detail,
+                    ruleMessages[detail],
+                    colIndex
+                ),
             });
         }
 
@@ -123,11 +202,40 @@ export class Engine {
             case 5: return "InvalidUtf8";
             case 6: return "MissingRequiredColumn";
             case 7: return "ExtraColumn";
+            case 8: return "PatternMismatch";
+            case 9: return "OutOfRange";
+            case 10: return "DuplicateValue";
+            case 11: return "ScaleExceeded";
+            case 12: return "TooManyDigits";
+            case 13: return "RuleViolation";
+            case 14: return "Forbidden";
+            case 15: return "NotInLookup";
+            case 16: return "MinLengthNotMet";
+            case 17: return "ExactLengthMismatch";
+            case 18: return "DisallowedCharacter";
+            case 19: return "FormulaInjection";
+            case 20: return "ControlCharacter";
+            case 21: return "RowTooShort";
+            case 22: return "RowTooLong";
+            case 23: return "UnexpectedQuote";
+            case 24: return "UnterminatedQuote";
+            case 25: return "FieldTooLarge";
+            case 26: return "RecordTooLarge";
+            case 27: return "DuplicateHeader";
+            case 28: return "EmptyHeader";
+            case 29: return "ColumnOutOfOrder";
             default: return "Unknown";
         }
     }
 
-    private static makeMessage(row: number, codeString: string, col?: string) {
+    private static makeMessage(
+        row: number,
+        codeString: string,
+        col?: string,
+        detail = 0,
+        ruleMessage?: string,
+        colIndex = 0
+    ) {
         const where = row === 0 ? "Header" : `Row ${row}`;
         const colPart = col ? `, column "${col}"` : "";
 
@@ -146,6 +254,50 @@ export class Engine {
                 return `${where}${colPart}: value not allowed`;
             case "InvalidUtf8":
                 return `${where}${colPart}: invalid text encoding`;
+            case "PatternMismatch":
+                return `${where}${colPart}: does not match the expected format`;
+            case "OutOfRange":
+                return `${where}${colPart}: value out of range`;
+            case "DuplicateValue":
+                return `${where}${colPart}: duplicate of row ${detail}`;
+            case "ScaleExceeded":
+                return `${where}${colPart}: too many decimal places`;
+            case "TooManyDigits":
+                return `${where}${colPart}: too many digits`;
+            case "RuleViolation":
+                return `${where}${colPart}: ${ruleMessage ?? "rule violated"}`;
+            case "Forbidden":
+                return `${where}${colPart}: must be empty here`;
+            case "NotInLookup":
+                return `${where}${colPart}: not found in the reference list`;
+            case "MinLengthNotMet":
+                return `${where}${colPart}: too short`;
+            case "ExactLengthMismatch":
+                return `${where}${colPart}: wrong length`;
+            case "DisallowedCharacter":
+                return `${where}${colPart}: contains a character that is not allowed`;
+            case "FormulaInjection":
+                return `${where}${colPart}: starts like a spreadsheet formula`;
+            case "ControlCharacter":
+                return `${where}${colPart}: contains a control character`;
+            case "RowTooShort":
+                return `${where}: row has too few fields (expected ${detail})`;
+            case "RowTooLong":
+                return `${where}: row has too many fields (expected ${detail})`;
+            case "UnexpectedQuote":
+                return `${where}${colPart}: unexpected quote character at byte ${detail}`;
+            case "UnterminatedQuote":
+                return `${where}${colPart}: quoted field opened at byte ${detail} is never closed`;
+            case "FieldTooLarge":
+                return `${where}${colPart}: field is too large (at byte ${detail})`;
+            case "RecordTooLarge":
+                return `${where}: row is too large (at byte ${detail})`;
+            case "DuplicateHeader":
+                return `${where}${colPart}: duplicate column header (first seen in column ${detail + 1})`;
+            case "EmptyHeader":
+                return `${where}${colPart}: empty column header`;
+            case "ColumnOutOfOrder":
+                return `${where}${colPart}: expected at position ${colIndex + 1}, found at ${detail + 1}`;
             default:
                 return `${where}${colPart}: validation error`;
         }
@@ -4,10 +4,31 @@ export interface Progress {
     done: boolean;
 }
 
+export interface Dialect {
+    encoding: string | null; // null while "auto" is still waiting for a BOM
+    delimiter: string | null; // null while "auto" is still waiting for data
+    quote: string;
+    hasHeaders: boolean;
+    sniffed: boolean;
+    headerLine: number | null; // physical line (XLSX: sheet row) of the header, once read
+}
+
+export interface HeaderMatch {
+    inputIndex: number;
+    input: string;
+    schemaIndex: number | null; // null when the header matched no column
+    column: string | null;
+    matchedBy: "name" | "alias" | null;
+}
+
 export interface PackedError {
+    // Physical line where the record starts (XLSX: sheet row), 0 for
+    // header-level. Breaking change: this used to be the 1-based data-row
+    // index, which left out the header, skipped lines and blank rows.
     row: number;
     col: number;
     code: number;
+    detail: number; // code-specific, 0 if unused
 }
 
 export type ErrorCodeString =
@@ -18,14 +39,57 @@ export type ErrorCodeString =
     | "InvalidUtf8"
     | "MissingRequiredColumn"
     | "ExtraColumn"
+    | "PatternMismatch"
+    | "OutOfRange"
+    | "DuplicateValue"
+    | "ScaleExceeded"
+    | "TooManyDigits"
+    | "RuleViolation"
+    | "Forbidden"
+    | "NotInLookup"
+    | "MinLengthNotMet"
+    | "ExactLengthMismatch"
+    | "DisallowedCharacter"
+    | "FormulaInjection"
+    | "ControlCharacter"
+    | "RowTooShort"
+    | "RowTooLong"
+    | "UnexpectedQuote"
+    | "UnterminatedQuote"
+    | "FieldTooLarge"
+    | "RecordTooLarge"
+    | "DuplicateHeader"
+    | "EmptyHeader"
+    | "ColumnOutOfOrder"
     | "Unknown";
 
+export interface ErrorDetail {
+    row: number;
+    col: number;
+    kind: "schema" | "input";
+    code: ErrorCodeString;
+    value: string | null; // raw field text, cut to the limit given to enableErrorDetails
+    valueTruncated: boolean;
+    line: number | null; // physical line where the record starts (null for XLSX)
+    byteOffset: number | null; // into the UTF-8 input as parsed (null for XLSX)
+    expected: string | null; // e.g. "one of: a, b" or "a date (YYYY-MM-DD)"
+}
+
 export type DecodedError = {
-    row: number; // 0 means header-level
+    // Physical line the record starts on (XLSX: sheet row); 0 means
+    // header-level. Was the data-row index before; see PackedError.row
+    row: number;
     code: number;
     codeString: ErrorCodeString;
     colIndex: number;
     colKind: "schema" | "input";
     columnName?: string;
+    // DuplicateValue: row of first occurrence; RuleViolation: rule index;
+    // length codes: measured length; DisallowedCharacter, FormulaInjection,
+    // ControlCharacter: offending code point; RowTooShort/RowTooLong: expected width;
+    // structural errors (UnexpectedQuote etc.): byte offset in the input;
+    // DuplicateHeader: input index of the first occurrence; ColumnOutOfOrder:
+    // actual input index (the schema column index is the expected position)
+    detail: number;
     message: string;
 };
@@ -4,8 +4,9 @@ export async function createEngine(
     wasmUrl: string,
     schema: object,
     maxErrors: number,
-    emitNormalized: boolean
+    emitNormalized: boolean,
+    now?: number
 ) {
     await initWasm(wasmUrl);
-    return await Engine.create(schema, maxErrors, emitNormalized);
+    return await Engine.create(schema, maxErrors, emitNormalized, now);
 }
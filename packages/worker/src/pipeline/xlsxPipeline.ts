@@ -0,0 +1,33 @@
+import type { Engine } from "@import-validator/core";
+import type { WorkerResponse } from "../protocol";
+
+const XLSX_MIME = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+export function isXlsx(file: File) {
+    return file.type === XLSX_MIME || /\.xlsx$/i.test(file.name);
+}
+
+export async function runXlsx(
+    file: File,
+    engine: Engine,
+    post: (msg: WorkerResponse) => void
+) {
+    // The zip central directory sits at the end of the file, so the
+    // workbook has to be handed over in one piece.
+    const bytes = new Uint8Array(await file.arrayBuffer());
+
+    const progress = engine.pushXlsx(bytes);
+    post({ type: "progress", progress });
+
+    const errors = engine.takeErrorsDecoded(1_000_000);
+    if (errors.length) {
+        post({ type: "errors", errors });
+    }
+
+    const normalized = engine.takeNormalized();
+    if (normalized.length) {
+        post({ type: "normalized", chunk: normalized });
+    }
+
+    post({ type: "done" });
+}
@@ -6,6 +6,8 @@ export type WorkerInit = {
     schema: object;
     maxErrors: number;
     emitNormalized: boolean;
+    now?: number; // epoch ms for relative date bounds; defaults to Date.now()
+    lookups?: Record<string, string[]>; // reference sets for `lookup` columns
 };
 
 export type WorkerValidate = {
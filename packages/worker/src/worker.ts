@@ -1,5 +1,6 @@
 import { createEngine } from "./loaders/wasmLoader";
 import { runCsv } from "./pipeline/csvPipeline";
+import { isXlsx, runXlsx } from "./pipeline/xlsxPipeline";
 import type { WorkerRequest, WorkerResponse } from "./protocol";
 
 let engine: any = null;
@@ -17,8 +18,12 @@ self.onmessage = async (e: MessageEvent<WorkerRequest>) => {
                 e.data.wasmUrl,
                 e.data.schema,
                 e.data.maxErrors,
-                e.data.emitNormalized
+                e.data.emitNormalized,
+                e.data.now ?? Date.now()
             );
+            for (const [name, values] of Object.entries(e.data.lookups ?? {})) {
+                engine.pushLookupValues(name, values);
+            }
 
             post({ type: "ready", columns: engine.schemaColumns() });
             return;
@@ -26,7 +31,11 @@ self.onmessage = async (e: MessageEvent<WorkerRequest>) => {
 
         if (e.data.type === "validate") {
             if (!engine) throw new Error("Engine not initialized");
-            await runCsv(e.data.file, engine, post);
+            if (isXlsx(e.data.file)) {
+                await runXlsx(e.data.file, engine, post);
+            } else {
+                await runCsv(e.data.file, engine, post);
+            }
             return;
         }
     } catch (err: any) {